@@ -1,3 +1,4 @@
+use std::env;
 use std::old_io::Command;
 use std::old_io::process::StdioContainer;
 #[cfg(target_os = "linux")]
@@ -5,6 +6,11 @@ use std::old_io::fs;
 
 #[cfg(target_os = "linux")]
 fn main() {
+    // nothing to build without the sdk feature
+    if env::var("CARGO_FEATURE_SDK").is_err() {
+        return;
+    }
+
     Command::new("make")
             .arg("-C").arg("modules/oculus_sdk_linux/")
             .stdout(StdioContainer::InheritFd(1))
@@ -21,6 +27,10 @@ fn main() {
 
 #[cfg(target_os = "macos")]
 fn main() {
+    if env::var("CARGO_FEATURE_SDK").is_err() {
+        return;
+    }
+
     Command::new("xcodebuild")
             .arg("-project")
             .arg("modules/oculus_sdk_mac/LibOVR/Projects/Mac/Xcode/LibOVR.xcodeproj")
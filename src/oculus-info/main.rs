@@ -1,57 +1,281 @@
 #![crate_name = "oculus-info"]
 
 extern crate ovr;
+extern crate cgmath;
+extern crate time;
 
+use std::env;
+use std::io::{self, Write};
+use std::process;
+
+use ovr::{HmdType, HmdDescription, SensorDescription, FovPort, Eye};
+#[cfg(feature = "sdk")]
 use ovr::{SensorCapabilities, Ovr};
+use ovr::simulated::{SimulatedHmd, Stationary, Sway};
+
+use report::{Format, Object, Value};
+use watch::{SinkFormat, WatchOptions};
+
+mod report;
+mod watch;
+
+// bumped whenever a field is renamed or removed from the report
+const SCHEMA_VERSION: i64 = 1;
+
+// exit codes, one for each stage that can fail
+const EXIT_USAGE: i32 = 1;
+const EXIT_INIT: i32 = 2;
+#[cfg_attr(not(feature = "sdk"), allow(dead_code))]
+const EXIT_NO_HMD: i32 = 3;
+#[cfg_attr(not(feature = "sdk"), allow(dead_code))]
+const EXIT_SENSOR_START: i32 = 4;
+#[cfg_attr(not(feature = "sdk"), allow(dead_code))]
+const EXIT_SENSOR_DESCRIPTION: i32 = 5;
+const EXIT_WATCH: i32 = 6;
+
+const USAGE: &'static str = "usage: oculus-info [--format json|toml|text] [--simulate dk1|dk2]
+                   [--watch [--rate <hz>] [--count <n>] [--csv <path> | --ndjson <path>]]";
+
+struct Options {
+    format: Format,
+    simulate: Option<HmdType>,
+    watch: Option<WatchOptions>
+}
+
+fn watch_options(options: &mut Options) -> &mut WatchOptions {
+    if options.watch.is_none() {
+        options.watch = Some(WatchOptions { rate: 10., count: None, sink: None });
+    }
+    options.watch.as_mut().unwrap()
+}
+
+fn parse_args() -> Result<Options, String> {
+    let mut options = Options {
+        format: Format::Text,
+        simulate: None,
+        watch: None
+    };
+
+    let mut args = env::args().skip(1);
+    while let Some(arg) = args.next() {
+        match &arg[..] {
+            "--format" => {
+                let value = try!(args.next().ok_or("--format needs a value".to_string()));
+                options.format = try!(Format::from_str(&value).ok_or(
+                    format!("unknown format: {}", value)
+                ));
+            }
+            "--simulate" => {
+                let value = try!(args.next().ok_or("--simulate needs a value".to_string()));
+                options.simulate = Some(match &value[..] {
+                    "dk1" => HmdType::DK1,
+                    "dk2" => HmdType::DK2,
+                    _ => return Err(format!("unknown hmd type to simulate: {}", value))
+                });
+            }
+            "--watch" => { watch_options(&mut options); }
+            "--rate" => {
+                let value = try!(args.next().ok_or("--rate needs a value".to_string()));
+                let rate = match value.parse::<f64>() {
+                    Ok(rate) if rate > 0. => rate,
+                    _ => return Err(format!("invalid rate: {}", value))
+                };
+                watch_options(&mut options).rate = rate;
+            }
+            "--count" => {
+                let value = try!(args.next().ok_or("--count needs a value".to_string()));
+                let count = try!(value.parse::<usize>().map_err(
+                    |_| format!("invalid count: {}", value)
+                ));
+                watch_options(&mut options).count = Some(count);
+            }
+            "--csv" | "--ndjson" => {
+                let path = try!(args.next().ok_or(format!("{} needs a path", arg)));
+                let format = if arg == "--csv" { SinkFormat::Csv } else { SinkFormat::NdJson };
+                watch_options(&mut options).sink = Some((format, path));
+            }
+            "--help" | "-h" => return Err(USAGE.to_string()),
+            _ => return Err(format!("unknown argument: {}", arg))
+        }
+    }
+
+    Ok(options)
+}
+
+fn fail(stage: i32, msg: String) -> ! {
+    let _ = writeln!(&mut io::stderr(), "{}", msg);
+    process::exit(stage);
+}
+
+fn degrees(tan: f32) -> f64 {
+    (tan as f64).atan().to_degrees()
+}
+
+fn fov_report(fov: &FovPort) -> Value {
+    Object::new()
+        .field("tangents", Object::new()
+            .field("up", fov.up)
+            .field("down", fov.down)
+            .field("left", fov.left)
+            .field("right", fov.right))
+        .field("degrees", Object::new()
+            .field("up", degrees(fov.up))
+            .field("down", degrees(fov.down))
+            .field("left", degrees(fov.left))
+            .field("right", degrees(fov.right)))
+        .build()
+}
+
+fn eye_name(eye: Eye) -> Value {
+    match eye {
+        Eye::Left => "left".into(),
+        Eye::Right => "right".into()
+    }
+}
+
+fn hmd_report(desc: &HmdDescription) -> Value {
+    let hmd_caps = desc.hmd_capabilities;
+    let sensor_caps = desc.sensor_capabilities;
+    let distortion_caps = desc.distortion_capabilities;
+
+    Object::new()
+        .field("type", format!("{:?}", desc.hmd_type))
+        .field("product_name", &desc.product_name[..])
+        .field("manufacturer", &desc.manufacture[..])
+        .field("resolution", Object::new()
+            .field("width", desc.resolution.x as i64)
+            .field("height", desc.resolution.y as i64))
+        .field("window_position", Object::new()
+            .field("x", desc.window_position.x as i64)
+            .field("y", desc.window_position.y as i64))
+        .field("eye_render_order", vec![eye_name(desc.eye_render_order[0]),
+                                        eye_name(desc.eye_render_order[1])])
+        .field("display_device_name", &desc.display_device_name[..])
+        .field("display_id", desc.display_id as i64)
+        .field("capabilities", Object::new()
+            .field("hmd", Object::new()
+                .field("present", hmd_caps.present())
+                .field("available", hmd_caps.available())
+                .field("low_persistence", hmd_caps.low_persistance())
+                .field("latency_test", hmd_caps.latency_test())
+                .field("dynamic_prediction", hmd_caps.dynamic_prediction())
+                .field("no_vsync", hmd_caps.no_vsync())
+                .field("no_restore", hmd_caps.no_restore()))
+            .field("sensor", Object::new()
+                .field("orientation", sensor_caps.orientation())
+                .field("yaw_correction", sensor_caps.yaw_correction())
+                .field("position", sensor_caps.position()))
+            .field("distortion", Object::new()
+                .field("chromatic", distortion_caps.chromatic())
+                .field("timewarp", distortion_caps.timewarp())
+                .field("vignette", distortion_caps.vignette())))
+        .field("eyes", Object::new()
+            .field("left", Object::new()
+                .field("default_fov", fov_report(&desc.eye_fovs.left.default_eye_fov))
+                .field("max_fov", fov_report(&desc.eye_fovs.left.max_eye_fov)))
+            .field("right", Object::new()
+                .field("default_fov", fov_report(&desc.eye_fovs.right.default_eye_fov))
+                .field("max_fov", fov_report(&desc.eye_fovs.right.max_eye_fov))))
+        .build()
+}
+
+fn sensor_report(sd: &SensorDescription) -> Value {
+    Object::new()
+        .field("vendor_id", sd.vendor_id as u16 as i64)
+        .field("product_id", sd.product_id as u16 as i64)
+        .field("serial_number", &sd.serial_number[..])
+        .build()
+}
+
+fn print_info(format: Format, sensor: &SensorDescription, hmd_desc: &HmdDescription) {
+    let report = Object::new()
+        .field("schema_version", SCHEMA_VERSION)
+        .field("hmd", hmd_report(hmd_desc))
+        .field("sensor", sensor_report(sensor))
+        .build();
+
+    print!("{}", report::render(&report, format));
+}
+
+fn simulate(format: Format, hmd_type: HmdType) {
+    let hmd = SimulatedHmd::new(hmd_type, Stationary::new());
+    print_info(format, &hmd.get_sensor_description(), &hmd.get_description());
+}
+
+fn watch_failed(err: io::Error) -> ! {
+    fail(EXIT_WATCH, format!("Could not write samples: {}", err))
+}
+
+// the simulated headset sways so there is something to watch, its clock
+// follows the sample times instead of the wall clock
+fn simulate_watch(options: &WatchOptions, hmd_type: HmdType) {
+    let mut hmd = SimulatedHmd::new(hmd_type, Sway::new());
+    let result = watch::watch(options, |t| {
+        hmd.set_time(t);
+        hmd.get_sensor_state(t)
+    });
+
+    if let Err(err) = result {
+        watch_failed(err);
+    }
+}
 
 fn main() {
-    let ovr = match Ovr::init() {
-        Some(ovr) => ovr,
-        None => {
-             println!("Could not initialize Oculus SDK");
-            return;           
+    let options = match parse_args() {
+        Ok(options) => options,
+        Err(msg) => fail(EXIT_USAGE, msg)
+    };
+
+    // --simulate reports a simulated headset without touching the SDK
+    if let Some(hmd_type) = options.simulate {
+        match options.watch {
+            Some(ref watch) => simulate_watch(watch, hmd_type),
+            None => simulate(options.format, hmd_type)
         }
+        return;
+    }
+
+    report_hmd(&options);
+}
+
+#[cfg(feature = "sdk")]
+fn report_hmd(options: &Options) {
+    let ovr = match Ovr::init() {
+        Ok(ovr) => ovr,
+        Err(err) => fail(EXIT_INIT, format!("Could not initialize Oculus SDK: {}", err))
     };
 
     let hmd = match ovr.first_hmd() {
-        Some(hmd) => hmd,
-        None => {
-            println!("Could not get hmd");
-            return;
-        }
+        Ok(hmd) => hmd,
+        Err(err) => fail(EXIT_NO_HMD, format!("Could not get hmd: {}", err))
     };
 
     let started = hmd.start_sensor(SensorCapabilities::new().set_orientation(true),
                                    SensorCapabilities::new().set_orientation(true));
 
-    if !started {
-        println!("Could not start sensor");
+    let hmd = match started {
+        Ok(hmd) => hmd,
+        Err(err) => fail(EXIT_SENSOR_START, format!("Could not start sensor: {}", err))
+    };
+
+    if let Some(ref watch) = options.watch {
+        if let Err(err) = watch::watch(watch, |_| hmd.get_sensor_state(ovr::get_time())) {
+            watch_failed(err);
+        }
         return;
     }
 
+    let sensor = match hmd.get_sensor_description() {
+        Ok(sd) => sd,
+        Err(err) => fail(EXIT_SENSOR_DESCRIPTION,
+                         format!("Failed to get sensor description: {}", err))
+    };
 
-    match hmd.get_sensor_description() {
-        Some(sd) => {
-            println!("Vendor id: {:x}", sd.vendor_id);
-            println!("Product id: {:x}", sd.product_id);
-            println!("Serial number: {:?}", sd.serial_number);
-        }
-        None => println!("Failed to get sensor description"),
-    }
+    print_info(options.format, &sensor, &hmd.get_description());
+}
 
-    let hmd_desc = hmd.get_description();
-
-    println!("Hmd Type: {:?}", hmd_desc.hmd_type);
-    println!("Product Name: {:?}", hmd_desc.product_name);
-    println!("Manufacture: {:?}", hmd_desc.manufacture);
-    println!("Hmd Capabilities: {:?}", hmd_desc.hmd_capabilities);
-    println!("Sensor Capabilities: {:?}", hmd_desc.sensor_capabilities);
-    println!("Distortion Capabilities: {:?}", hmd_desc.distortion_capabilities);
-    println!("Resolution: {:?}", hmd_desc.resolution);
-    println!("Window Position: {:?}", hmd_desc.window_position);
-    println!("right: {:?}", hmd_desc.eye_fovs.right);
-    println!("left {:?}", hmd_desc.eye_fovs.left);
-    println!("Eyes render order: [{:?}, {:?}]", hmd_desc.eye_render_order[0], hmd_desc.eye_render_order[1]);
-    println!("Display device name: {:?}", hmd_desc.display_device_name);
-    println!("Display id: {:?}", hmd_desc.display_id);
-}
\ No newline at end of file
+// built without the SDK there is no real headset to report
+#[cfg(not(feature = "sdk"))]
+fn report_hmd(_: &Options) {
+    fail(EXIT_INIT, "Built without the Oculus SDK, only --simulate is available".to_string())
+}
@@ -0,0 +1,511 @@
+// A small tree of values and the writers for the output formats oculus-info
+// supports. Objects keep their insertion order so the output is stable.
+
+use std::fmt::Write;
+
+#[derive(Debug, Clone)]
+pub enum Value {
+    Str(String),
+    Int(i64),
+    Float(f64),
+    Bool(bool),
+    List(Vec<Value>),
+    Object(Vec<(String, Value)>)
+}
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum Format {
+    Json,
+    Toml,
+    Text
+}
+
+impl Format {
+    pub fn from_str(s: &str) -> Option<Format> {
+        match s {
+            "json" => Some(Format::Json),
+            "toml" => Some(Format::Toml),
+            "text" => Some(Format::Text),
+            _ => None
+        }
+    }
+}
+
+// builder for Value::Object
+pub struct Object {
+    fields: Vec<(String, Value)>
+}
+
+impl Object {
+    pub fn new() -> Object {
+        Object { fields: Vec::new() }
+    }
+
+    pub fn field<V: Into<Value>>(mut self, key: &str, value: V) -> Object {
+        self.fields.push((key.to_string(), value.into()));
+        self
+    }
+
+    pub fn build(self) -> Value {
+        Value::Object(self.fields)
+    }
+}
+
+impl From<Object> for Value {
+    fn from(o: Object) -> Value { o.build() }
+}
+
+impl<'a> From<&'a str> for Value {
+    fn from(s: &'a str) -> Value { Value::Str(s.to_string()) }
+}
+
+impl From<String> for Value {
+    fn from(s: String) -> Value { Value::Str(s) }
+}
+
+impl From<i64> for Value {
+    fn from(i: i64) -> Value { Value::Int(i) }
+}
+
+impl From<f64> for Value {
+    fn from(f: f64) -> Value { Value::Float(f) }
+}
+
+impl From<f32> for Value {
+    fn from(f: f32) -> Value { Value::Float(f as f64) }
+}
+
+impl From<bool> for Value {
+    fn from(b: bool) -> Value { Value::Bool(b) }
+}
+
+impl From<Vec<Value>> for Value {
+    fn from(l: Vec<Value>) -> Value { Value::List(l) }
+}
+
+pub fn render(value: &Value, format: Format) -> String {
+    let mut out = String::new();
+    match format {
+        Format::Json => {
+            json(&mut out, value, 0);
+            out.push('\n');
+        }
+        Format::Toml => toml(&mut out, value, ""),
+        Format::Text => text(&mut out, value, 0)
+    }
+    out
+}
+
+// json on a single line, for streams with one value per line
+pub fn render_line(value: &Value) -> String {
+    let mut out = String::new();
+    json_line(&mut out, value);
+    out
+}
+
+fn json_line(out: &mut String, value: &Value) {
+    match *value {
+        Value::Object(ref fields) => {
+            out.push('{');
+            for (i, &(ref key, ref v)) in fields.iter().enumerate() {
+                if i != 0 { out.push(','); }
+                quote(out, key);
+                out.push(':');
+                json_line(out, v);
+            }
+            out.push('}');
+        }
+        _ => scalar(out, value, Format::Json)
+    }
+}
+
+fn quote(out: &mut String, s: &str) {
+    out.push('"');
+    for c in s.chars() {
+        match c {
+            '"' => out.push_str("\\\""),
+            '\\' => out.push_str("\\\\"),
+            '\n' => out.push_str("\\n"),
+            '\r' => out.push_str("\\r"),
+            '\t' => out.push_str("\\t"),
+            c if (c as u32) < 0x20 => { write!(out, "\\u{:04x}", c as u32).unwrap(); }
+            c => out.push(c)
+        }
+    }
+    out.push('"');
+}
+
+fn float(out: &mut String, f: f64, format: Format) {
+    if f.is_finite() {
+        // always keep a decimal point so readers see a float, f64's Display
+        // never uses an exponent so whole numbers of any size print as digits
+        if f == f.trunc() {
+            write!(out, "{}.0", f).unwrap();
+        } else {
+            write!(out, "{}", f).unwrap();
+        }
+    } else if format == Format::Json {
+        // json has no literal for them
+        out.push_str("null");
+    } else if f.is_nan() {
+        out.push_str("nan");
+    } else if f > 0. {
+        out.push_str("inf");
+    } else {
+        out.push_str("-inf");
+    }
+}
+
+fn scalar(out: &mut String, value: &Value, format: Format) {
+    match *value {
+        Value::Str(ref s) => quote(out, s),
+        Value::Int(i) => { write!(out, "{}", i).unwrap(); }
+        Value::Float(f) => float(out, f, format),
+        Value::Bool(b) => { write!(out, "{}", b).unwrap(); }
+        Value::List(ref l) => {
+            out.push('[');
+            for (i, v) in l.iter().enumerate() {
+                if i != 0 { out.push_str(", "); }
+                scalar(out, v, format);
+            }
+            out.push(']');
+        }
+        // only reached for objects nested in lists, which we never emit
+        Value::Object(_) => out.push_str("{}")
+    }
+}
+
+fn indent(out: &mut String, depth: usize) {
+    for _ in 0..depth {
+        out.push_str("  ");
+    }
+}
+
+fn json(out: &mut String, value: &Value, depth: usize) {
+    match *value {
+        Value::Object(ref fields) => {
+            out.push_str("{\n");
+            for (i, &(ref key, ref v)) in fields.iter().enumerate() {
+                indent(out, depth + 1);
+                quote(out, key);
+                out.push_str(": ");
+                json(out, v, depth + 1);
+                if i + 1 != fields.len() {
+                    out.push(',');
+                }
+                out.push('\n');
+            }
+            indent(out, depth);
+            out.push('}');
+        }
+        _ => scalar(out, value, Format::Json)
+    }
+}
+
+fn toml(out: &mut String, value: &Value, path: &str) {
+    let fields = match *value {
+        Value::Object(ref fields) => fields,
+        _ => return
+    };
+
+    // toml wants the plain keys of a table before any sub tables
+    for &(ref key, ref v) in fields.iter() {
+        match *v {
+            Value::Object(_) => (),
+            _ => {
+                out.push_str(key);
+                out.push_str(" = ");
+                scalar(out, v, Format::Toml);
+                out.push('\n');
+            }
+        }
+    }
+
+    for &(ref key, ref v) in fields.iter() {
+        match *v {
+            Value::Object(_) => {
+                let sub = if path.len() == 0 {
+                    key.clone()
+                } else {
+                    format!("{}.{}", path, key)
+                };
+                write!(out, "\n[{}]\n", sub).unwrap();
+                toml(out, v, &sub);
+            }
+            _ => ()
+        }
+    }
+}
+
+fn text(out: &mut String, value: &Value, depth: usize) {
+    let fields = match *value {
+        Value::Object(ref fields) => fields,
+        _ => return
+    };
+
+    for &(ref key, ref v) in fields.iter() {
+        indent(out, depth);
+        out.push_str(key);
+        out.push(':');
+        match *v {
+            Value::Object(_) => {
+                out.push('\n');
+                text(out, v, depth + 1);
+            }
+            Value::Str(ref s) => { write!(out, " {}\n", s).unwrap(); }
+            _ => {
+                out.push(' ');
+                scalar(out, v, Format::Text);
+                out.push('\n');
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::f64;
+    use std::iter::Peekable;
+    use std::str::Chars;
+
+    use super::{render, render_line, Format, Object, Value};
+
+    // what the readers below give back, json has a null where toml has
+    // nan and inf
+    #[derive(Debug)]
+    enum Parsed {
+        Null,
+        Str(String),
+        Int(i64),
+        Float(f64),
+        Bool(bool),
+        List(Vec<Parsed>),
+        Object(Vec<(String, Parsed)>)
+    }
+
+    fn skip_space(chars: &mut Peekable<Chars>) {
+        while chars.peek().map_or(false, |c| c.is_whitespace()) {
+            chars.next();
+        }
+    }
+
+    fn read_string(chars: &mut Peekable<Chars>) -> String {
+        assert_eq!(chars.next(), Some('"'));
+        let mut s = String::new();
+        loop {
+            match chars.next().unwrap() {
+                '"' => return s,
+                '\\' => match chars.next().unwrap() {
+                    'n' => s.push('\n'),
+                    'r' => s.push('\r'),
+                    't' => s.push('\t'),
+                    'u' => {
+                        let hex: String = chars.by_ref().take(4).collect();
+                        let code = u32::from_str_radix(&hex, 16).unwrap();
+                        s.push(::std::char::from_u32(code).unwrap());
+                    }
+                    c => s.push(c)
+                },
+                c => s.push(c)
+            }
+        }
+    }
+
+    // numbers and the bare words of both formats
+    fn read_word(chars: &mut Peekable<Chars>) -> Parsed {
+        let mut word = String::new();
+        while chars.peek().map_or(false, |&c| c.is_alphanumeric() || "+-.".contains(c)) {
+            word.push(chars.next().unwrap());
+        }
+        match &word[..] {
+            "null" => Parsed::Null,
+            "true" => Parsed::Bool(true),
+            "false" => Parsed::Bool(false),
+            "nan" => Parsed::Float(f64::NAN),
+            "inf" => Parsed::Float(f64::INFINITY),
+            "-inf" => Parsed::Float(f64::NEG_INFINITY),
+            w if w.contains('.') || w.contains('e') => Parsed::Float(w.parse().unwrap()),
+            w => Parsed::Int(w.parse().unwrap())
+        }
+    }
+
+    fn read_value(chars: &mut Peekable<Chars>) -> Parsed {
+        skip_space(chars);
+        match *chars.peek().unwrap() {
+            '"' => Parsed::Str(read_string(chars)),
+            '[' => {
+                chars.next();
+                let mut list = Vec::new();
+                loop {
+                    skip_space(chars);
+                    match *chars.peek().unwrap() {
+                        ']' => { chars.next(); return Parsed::List(list); }
+                        ',' => { chars.next(); }
+                        _ => list.push(read_value(chars))
+                    }
+                }
+            }
+            '{' => {
+                chars.next();
+                let mut fields = Vec::new();
+                loop {
+                    skip_space(chars);
+                    match *chars.peek().unwrap() {
+                        '}' => { chars.next(); return Parsed::Object(fields); }
+                        ',' => { chars.next(); }
+                        _ => {
+                            let key = read_string(chars);
+                            skip_space(chars);
+                            assert_eq!(chars.next(), Some(':'));
+                            fields.push((key, read_value(chars)));
+                        }
+                    }
+                }
+            }
+            _ => read_word(chars)
+        }
+    }
+
+    fn read_json(s: &str) -> Parsed {
+        let mut chars = s.chars().peekable();
+        let value = read_value(&mut chars);
+        skip_space(&mut chars);
+        assert!(chars.next().is_none());
+        value
+    }
+
+    fn table<'a>(root: &'a mut Vec<(String, Parsed)>, path: &[String]) -> &'a mut Vec<(String, Parsed)> {
+        if path.is_empty() {
+            return root;
+        }
+        let i = match root.iter().position(|&(ref k, _)| *k == path[0]) {
+            Some(i) => i,
+            None => {
+                root.push((path[0].clone(), Parsed::Object(Vec::new())));
+                root.len() - 1
+            }
+        };
+        match root[i].1 {
+            Parsed::Object(ref mut fields) => table(fields, &path[1..]),
+            _ => panic!("{} is not a table", path[0])
+        }
+    }
+
+    fn read_toml(s: &str) -> Parsed {
+        let mut root = Vec::new();
+        let mut path: Vec<String> = Vec::new();
+        for line in s.lines() {
+            if line.is_empty() {
+                continue;
+            }
+            if line.starts_with("[") {
+                path = line[1..line.len() - 1].split('.').map(|k| k.to_string()).collect();
+                table(&mut root, &path);
+                continue;
+            }
+            let eq = line.find(" = ").unwrap();
+            let mut chars = line[eq + 3..].chars().peekable();
+            let value = read_value(&mut chars);
+            assert!(chars.next().is_none());
+            table(&mut root, &path).push((line[..eq].to_string(), value));
+        }
+        Parsed::Object(root)
+    }
+
+    fn same_float(a: f64, b: f64) -> bool {
+        a == b || (a.is_nan() && b.is_nan())
+    }
+
+    // compares what was written with what was read back, in json the
+    // floats that are not finite have to be null
+    fn assert_round_trip(value: &Value, parsed: &Parsed, json: bool) {
+        match (value, parsed) {
+            (&Value::Float(f), &Parsed::Null) if json => assert!(!f.is_finite()),
+            (&Value::Float(f), &Parsed::Float(p)) => {
+                assert!(!json || f.is_finite());
+                assert!(same_float(f, p), "wrote {} read {}", f, p);
+            }
+            (&Value::Str(ref s), &Parsed::Str(ref p)) => assert_eq!(s, p),
+            (&Value::Int(i), &Parsed::Int(p)) => assert_eq!(i, p),
+            (&Value::Bool(b), &Parsed::Bool(p)) => assert_eq!(b, p),
+            (&Value::List(ref l), &Parsed::List(ref p)) => {
+                assert_eq!(l.len(), p.len());
+                for (v, p) in l.iter().zip(p.iter()) {
+                    assert_round_trip(v, p, json);
+                }
+            }
+            (&Value::Object(ref fields), &Parsed::Object(ref p)) => {
+                assert_eq!(fields.len(), p.len());
+                for (&(ref key, ref v), &(ref pkey, ref p)) in fields.iter().zip(p.iter()) {
+                    assert_eq!(key, pkey);
+                    assert_round_trip(v, p, json);
+                }
+            }
+            _ => panic!("wrote {:?} read {:?}", value, parsed)
+        }
+    }
+
+    // the sub objects come last, that is the order toml writes them in
+    fn report() -> Value {
+        Object::new()
+            .field("name", "Oculus \"Rift\"\n\tDK1 \\ \u{1}")
+            .field("index", -3i64)
+            .field("connected", true)
+            .field("half", 0.5)
+            .field("whole", 2.)
+            .field("negative", -0.25)
+            .field("small", 1e-7)
+            .field("large", 1e15)
+            .field("larger", 1e16)
+            .field("huge", 123456789012345678.)
+            .field("not_a_number", f64::NAN)
+            .field("infinite", f64::INFINITY)
+            .field("negative_infinite", f64::NEG_INFINITY)
+            .field("floats", vec![Value::from(1.), Value::from(f64::NAN), Value::from(0.125f32)])
+            .field("empty", Vec::<Value>::new())
+            .field("eye", Object::new()
+                   .field("fov", vec![Value::from(1.0), Value::from(1.25)])
+                   .field("viewport", Object::new()
+                          .field("width", 640i64)
+                          .field("height", 800i64)))
+            .field("sensor", Object::new().field("temperature", 35.5))
+            .build()
+    }
+
+    #[test]
+    fn json_round_trips() {
+        let value = report();
+        assert_round_trip(&value, &read_json(&render(&value, Format::Json)), true);
+    }
+
+    #[test]
+    fn json_lines_round_trip() {
+        let value = report();
+        let line = render_line(&value);
+        assert!(!line.contains('\n'));
+        assert_round_trip(&value, &read_json(&line), true);
+    }
+
+    #[test]
+    fn toml_round_trips() {
+        let value = report();
+        assert_round_trip(&value, &read_toml(&render(&value, Format::Toml)), false);
+    }
+
+    #[test]
+    fn whole_floats_keep_their_decimal_point() {
+        let value = Object::new().field("large", 1e16).field("zero", 0.).build();
+        assert_eq!(render_line(&value), "{\"large\":10000000000000000.0,\"zero\":0.0}");
+    }
+
+    #[test]
+    fn floats_that_are_not_finite() {
+        let value = Object::new()
+            .field("a", f64::NAN)
+            .field("b", f64::INFINITY)
+            .field("c", f64::NEG_INFINITY)
+            .build();
+        assert_eq!(render_line(&value), "{\"a\":null,\"b\":null,\"c\":null}");
+        assert_eq!(render(&value, Format::Toml), "a = nan\nb = inf\nc = -inf\n");
+    }
+}
@@ -0,0 +1,136 @@
+// --watch, prints the sensor state continuously and optionally streams every
+// sample to a csv or newline delimited json file.
+
+use std::fs::File;
+use std::io::{self, BufWriter, Write};
+use std::thread;
+
+use ovr::{SensorState, PoseState};
+use cgmath::Vector3;
+use time;
+
+use report::{self, Object, Value};
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum SinkFormat {
+    Csv,
+    NdJson
+}
+
+pub struct WatchOptions {
+    // samples per second
+    pub rate: f64,
+    // stop after this many samples, run forever when None
+    pub count: Option<usize>,
+    pub sink: Option<(SinkFormat, String)>
+}
+
+const CSV_HEADER: &'static str =
+    "time,qw,qx,qy,qz,yaw,pitch,roll,px,py,pz,avx,avy,avz,lvx,lvy,lvz,temperature,status";
+
+fn vec3_value(v: Vector3<f32>) -> Value {
+    vec![Value::from(v.x), Value::from(v.y), Value::from(v.z)].into()
+}
+
+fn sample_value(state: &SensorState) -> Value {
+    let ps = &state.recorded;
+    let q = ps.pose.orientation;
+    let (yaw, pitch, roll) = ps.pose.yaw_pitch_roll();
+    let status: Vec<Value> = state.status_flags.names().iter().map(|&n| n.into()).collect();
+
+    Object::new()
+        .field("time", ps.time_in_seconds)
+        .field("orientation", vec![Value::from(q.s), Value::from(q.v.x),
+                                   Value::from(q.v.y), Value::from(q.v.z)])
+        .field("yaw_pitch_roll", vec![Value::from((yaw as f64).to_degrees()),
+                                      Value::from((pitch as f64).to_degrees()),
+                                      Value::from((roll as f64).to_degrees())])
+        .field("position", vec3_value(ps.pose.position))
+        .field("angular_velocity", vec3_value(ps.angular_velocity))
+        .field("linear_velocity", vec3_value(ps.linear_velocity))
+        .field("temperature", state.temperature)
+        .field("status", status)
+        .build()
+}
+
+fn csv_row(state: &SensorState) -> String {
+    let ps: &PoseState = &state.recorded;
+    let q = ps.pose.orientation;
+    let (yaw, pitch, roll) = ps.pose.yaw_pitch_roll();
+    let p = ps.pose.position;
+    let av = ps.angular_velocity;
+    let lv = ps.linear_velocity;
+
+    format!("{},{},{},{},{},{},{},{},{},{},{},{},{},{},{},{},{},{},{}",
+            ps.time_in_seconds, q.s, q.v.x, q.v.y, q.v.z,
+            (yaw as f64).to_degrees(), (pitch as f64).to_degrees(), (roll as f64).to_degrees(),
+            p.x, p.y, p.z, av.x, av.y, av.z, lv.x, lv.y, lv.z,
+            state.temperature, state.status_flags.names().join("|"))
+}
+
+fn print_sample(state: &SensorState) {
+    let ps = &state.recorded;
+    let q = ps.pose.orientation;
+    let (yaw, pitch, roll) = ps.pose.yaw_pitch_roll();
+    let p = ps.pose.position;
+    let av = ps.angular_velocity;
+    let lv = ps.linear_velocity;
+
+    println!("t {:10.4}  q ({:7.4} {:7.4} {:7.4} {:7.4})  ypr ({:7.2} {:7.2} {:7.2})  \
+              pos ({:7.4} {:7.4} {:7.4})  ang vel ({:7.3} {:7.3} {:7.3})  \
+              lin vel ({:7.3} {:7.3} {:7.3})  {:5.1}C  [{}]",
+             ps.time_in_seconds, q.s, q.v.x, q.v.y, q.v.z,
+             (yaw as f64).to_degrees(), (pitch as f64).to_degrees(), (roll as f64).to_degrees(),
+             p.x, p.y, p.z, av.x, av.y, av.z, lv.x, lv.y, lv.z,
+             state.temperature, state.status_flags.names().join(", "));
+}
+
+// calls sample with the number of seconds since the watch started until
+// count samples have been taken. Samples are taken at fixed deadlines from
+// the start, so the time spent sampling and writing does not lower the rate,
+// and the sink is flushed after every row so stopping with Ctrl-C keeps
+// everything written so far.
+pub fn watch<F>(options: &WatchOptions, mut sample: F) -> io::Result<()>
+    where F: FnMut(f64) -> SensorState {
+
+    let mut sink = match options.sink {
+        Some((format, ref path)) => {
+            let mut out = BufWriter::new(try!(File::create(path)));
+            if format == SinkFormat::Csv {
+                try!(writeln!(out, "{}", CSV_HEADER));
+            }
+            Some((format, out))
+        }
+        None => None
+    };
+
+    let interval = 1. / options.rate;
+    let start = time::precise_time_s();
+    let mut taken = 0;
+    while options.count.map_or(true, |count| taken < count) {
+        let deadline = start + taken as f64 * interval;
+        let now = time::precise_time_s();
+        if deadline > now {
+            thread::sleep_ms(((deadline - now) * 1000.) as u32);
+        }
+
+        let state = sample(taken as f64 * interval);
+        print_sample(&state);
+
+        match sink {
+            Some((SinkFormat::Csv, ref mut out)) => {
+                try!(writeln!(out, "{}", csv_row(&state)));
+                try!(out.flush());
+            }
+            Some((SinkFormat::NdJson, ref mut out)) => {
+                try!(writeln!(out, "{}", report::render_line(&sample_value(&state))));
+                try!(out.flush());
+            }
+            None => ()
+        }
+
+        taken += 1;
+    }
+
+    Ok(())
+}
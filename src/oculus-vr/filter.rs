@@ -0,0 +1,515 @@
+// Smoothing filters for streams of pose states, mostly for the jitter of
+// camera tracked position at rest.
+//
+// Filters are fed states in time order and use time_in_seconds for their
+// time steps, a state that is not newer than the previous one returns the
+// previous output again. Fed through filter_sensor_state a filter resets
+// itself whenever tracking is lost, so it does not smooth across the jump
+// when tracking comes back.
+
+use std::f64::consts::PI;
+
+use cgmath::Vector3;
+
+use math::{vec3_sub, vec3_scale, vec3_length, vec3_lerp, quat_mul,
+           quat_conjugate, quat_slerp, quat_to_rotation_vector};
+use super::{Pose, PoseState, SensorState, Status};
+
+pub trait PoseFilter {
+    // the filtered version of the next state in the stream
+    fn filter(&mut self, state: &PoseState) -> PoseState;
+
+    // forgets the stream, the next state passes through unchanged
+    fn reset(&mut self);
+
+    // filters the recorded state, while tracking is lost the filter is
+    // reset and the state passes through unchanged
+    fn filter_sensor_state(&mut self, state: &SensorState) -> PoseState {
+        if tracking_lost(&state.status_flags) {
+            self.reset();
+            state.recorded
+        } else {
+            self.filter(&state.recorded)
+        }
+    }
+}
+
+// orientation was lost, or the camera is connected but lost the headset
+fn tracking_lost(status: &Status) -> bool {
+    !status.orientation_tracked() ||
+        (status.position_connected() && !status.position_tracked())
+}
+
+fn with_pose(state: &PoseState, pose: Pose) -> PoseState {
+    PoseState { pose: pose, .. *state }
+}
+
+// smoothing factor of a first order low pass filter with the given cutoff
+// frequency in Hz for a time step of dt seconds
+fn alpha(cutoff: f64, dt: f64) -> f32 {
+    let tau = 1. / (2. * PI * cutoff);
+    (1. / (1. + tau / dt)) as f32
+}
+
+// The parameters of one channel of a one euro filter. At rest the cutoff is
+// min_cutoff Hz and it rises by beta Hz for each unit of speed, so slow
+// movement is smoothed heavily and fast movement has little lag.
+#[derive(Debug, Copy, Clone)]
+pub struct OneEuro {
+    pub min_cutoff: f64,
+    pub beta: f64,
+    pub derivative_cutoff: f64
+}
+
+impl OneEuro {
+    pub fn new(min_cutoff: f64, beta: f64) -> OneEuro {
+        OneEuro {
+            min_cutoff: min_cutoff,
+            beta: beta,
+            derivative_cutoff: 1.
+        }
+    }
+}
+
+struct OneEuroState {
+    last: PoseState,
+    angular_speed: f32,
+    linear_velocity: Vector3<f32>
+}
+
+// A one euro filter, see Casiez et al. 2012. Orientation and position have
+// their own parameters since their speeds are in rad/s and m/s.
+pub struct OneEuroFilter {
+    pub orientation: OneEuro,
+    pub position: OneEuro,
+    state: Option<OneEuroState>
+}
+
+impl OneEuroFilter {
+    pub fn new(orientation: OneEuro, position: OneEuro) -> OneEuroFilter {
+        OneEuroFilter {
+            orientation: orientation,
+            position: position,
+            state: None
+        }
+    }
+}
+
+impl PoseFilter for OneEuroFilter {
+    fn filter(&mut self, state: &PoseState) -> PoseState {
+        if self.state.is_none() {
+            self.state = Some(OneEuroState {
+                last: *state,
+                angular_speed: 0.,
+                linear_velocity: Vector3::new(0., 0., 0.)
+            });
+            return *state;
+        }
+        let s = self.state.as_mut().unwrap();
+
+        let dt = state.time_in_seconds - s.last.time_in_seconds;
+        if dt <= 0. {
+            return s.last;
+        }
+        let last = s.last.pose;
+
+        // orientation, the speed is the angle to the last output per second
+        let delta = quat_to_rotation_vector(quat_mul(quat_conjugate(last.orientation),
+                                                     state.pose.orientation));
+        let speed = vec3_length(delta) / dt as f32;
+        let a = alpha(self.orientation.derivative_cutoff, dt);
+        s.angular_speed += (speed - s.angular_speed) * a;
+        let cutoff = self.orientation.min_cutoff + self.orientation.beta * s.angular_speed as f64;
+        let orientation = quat_slerp(last.orientation, state.pose.orientation, alpha(cutoff, dt));
+
+        // position
+        let velocity = vec3_scale(vec3_sub(state.pose.position, last.position), 1. / dt as f32);
+        let a = alpha(self.position.derivative_cutoff, dt);
+        s.linear_velocity = vec3_lerp(s.linear_velocity, velocity, a);
+        let cutoff = self.position.min_cutoff +
+            self.position.beta * vec3_length(s.linear_velocity) as f64;
+        let position = vec3_lerp(last.position, state.pose.position, alpha(cutoff, dt));
+
+        s.last = with_pose(state, Pose { orientation: orientation, position: position });
+        s.last
+    }
+
+    fn reset(&mut self) {
+        self.state = None;
+    }
+}
+
+// An exponential moving average, slerped for the orientation. The time
+// constants are in seconds, after one time constant a step in the input
+// is followed by about 63%, 0 disables the smoothing of that channel.
+pub struct ExponentialFilter {
+    pub orientation_time_constant: f64,
+    pub position_time_constant: f64,
+    last: Option<PoseState>
+}
+
+impl ExponentialFilter {
+    pub fn new(orientation_time_constant: f64, position_time_constant: f64) -> ExponentialFilter {
+        ExponentialFilter {
+            orientation_time_constant: orientation_time_constant,
+            position_time_constant: position_time_constant,
+            last: None
+        }
+    }
+}
+
+fn follow(dt: f64, time_constant: f64) -> f32 {
+    if time_constant <= 0. {
+        1.
+    } else {
+        (1. - (-dt / time_constant).exp()) as f32
+    }
+}
+
+impl PoseFilter for ExponentialFilter {
+    fn filter(&mut self, state: &PoseState) -> PoseState {
+        let last = match self.last {
+            Some(last) => last,
+            None => {
+                self.last = Some(*state);
+                return *state;
+            }
+        };
+
+        let dt = state.time_in_seconds - last.time_in_seconds;
+        if dt <= 0. {
+            return last;
+        }
+
+        let pose = Pose {
+            orientation: quat_slerp(last.pose.orientation, state.pose.orientation,
+                                    follow(dt, self.orientation_time_constant)),
+            position: vec3_lerp(last.pose.position, state.pose.position,
+                                follow(dt, self.position_time_constant))
+        };
+        let filtered = with_pose(state, pose);
+        self.last = Some(filtered);
+        filtered
+    }
+
+    fn reset(&mut self) {
+        self.last = None;
+    }
+}
+
+// position and velocity estimate along one axis and its covariance
+#[derive(Copy, Clone)]
+struct Axis {
+    x: f64,
+    v: f64,
+    p00: f64,
+    p01: f64,
+    p11: f64
+}
+
+impl Axis {
+    fn new(x: f64, measurement_noise: f64) -> Axis {
+        Axis { x: x, v: 0., p00: measurement_noise, p01: 0., p11: 1. }
+    }
+
+    fn step(&mut self, z: f64, dt: f64, process_noise: f64, measurement_noise: f64) {
+        // predict with constant velocity, the process noise is white
+        // acceleration with the given spectral density
+        let q = process_noise;
+        self.x += self.v * dt;
+        self.p00 += 2. * dt * self.p01 + dt * dt * self.p11 + q * dt * dt * dt / 3.;
+        self.p01 += dt * self.p11 + q * dt * dt / 2.;
+        self.p11 += q * dt;
+
+        // update with the measured position
+        let s = self.p00 + measurement_noise;
+        let k0 = self.p00 / s;
+        let k1 = self.p01 / s;
+        let y = z - self.x;
+        self.x += k0 * y;
+        self.v += k1 * y;
+        self.p11 -= k1 * self.p01;
+        self.p01 *= 1. - k0;
+        self.p00 *= 1. - k0;
+    }
+}
+
+// A constant velocity Kalman filter for the position, each axis is filtered
+// on its own. The orientation passes through unchanged and the linear
+// velocity of the output is the filter's estimate.
+//
+// process_noise is the spectral density of the unmodelled acceleration in
+// m^2/s^3, measurement_noise the variance of the measured position in m^2.
+pub struct KalmanFilter {
+    pub process_noise: f64,
+    pub measurement_noise: f64,
+    axes: Option<([Axis; 3], f64)>
+}
+
+impl KalmanFilter {
+    pub fn new(process_noise: f64, measurement_noise: f64) -> KalmanFilter {
+        KalmanFilter {
+            process_noise: process_noise,
+            measurement_noise: measurement_noise,
+            axes: None
+        }
+    }
+}
+
+impl PoseFilter for KalmanFilter {
+    fn filter(&mut self, state: &PoseState) -> PoseState {
+        let p = state.pose.position;
+        let measured = [p.x as f64, p.y as f64, p.z as f64];
+
+        if self.axes.is_none() {
+            let r = self.measurement_noise;
+            self.axes = Some(([Axis::new(measured[0], r), Axis::new(measured[1], r),
+                               Axis::new(measured[2], r)], state.time_in_seconds));
+            return *state;
+        }
+        let &mut (ref mut axes, ref mut time) = self.axes.as_mut().unwrap();
+
+        let dt = state.time_in_seconds - *time;
+        if dt > 0. {
+            *time = state.time_in_seconds;
+            for (axis, &z) in axes.iter_mut().zip(measured.iter()) {
+                axis.step(z, dt, self.process_noise, self.measurement_noise);
+            }
+        }
+
+        PoseState {
+            pose: Pose {
+                orientation: state.pose.orientation,
+                position: Vector3::new(axes[0].x as f32, axes[1].x as f32, axes[2].x as f32)
+            },
+            linear_velocity: Vector3::new(axes[0].v as f32, axes[1].v as f32, axes[2].v as f32),
+            .. *state
+        }
+    }
+
+    fn reset(&mut self) {
+        self.axes = None;
+    }
+}
+
+// runs a state through filters one after the other, e.g. a Kalman filter
+// for the position followed by an exponential filter for the orientation
+pub struct Chain<A, B> {
+    pub first: A,
+    pub second: B
+}
+
+impl<A: PoseFilter, B: PoseFilter> PoseFilter for Chain<A, B> {
+    fn filter(&mut self, state: &PoseState) -> PoseState {
+        let state = self.first.filter(state);
+        self.second.filter(&state)
+    }
+
+    fn reset(&mut self) {
+        self.first.reset();
+        self.second.reset();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use cgmath::{Quaternion, Vector3};
+
+    use super::{PoseFilter, OneEuro, OneEuroFilter, ExponentialFilter, KalmanFilter, Chain, alpha};
+    use super::super::{ll, Pose, PoseState, SensorState, Status};
+
+    const DT: f64 = 0.01;
+
+    fn yaw(angle: f32) -> Quaternion<f32> {
+        Quaternion::new((angle * 0.5).cos(), 0., (angle * 0.5).sin(), 0.)
+    }
+
+    fn yaw_of(q: Quaternion<f32>) -> f32 {
+        2. * q.v.y.atan2(q.s)
+    }
+
+    fn state(time: f64, x: f32, angle: f32) -> PoseState {
+        let zero = Vector3::new(0., 0., 0.);
+        PoseState {
+            pose: Pose {
+                orientation: yaw(angle),
+                position: Vector3::new(x, 0., 0.)
+            },
+            angular_velocity: zero,
+            linear_velocity: zero,
+            angular_acceleration: zero,
+            linear_acceleration: zero,
+            time_in_seconds: time
+        }
+    }
+
+    // feeds a step from 0 to 1 m and 0 to 1 rad at time 0 and returns the
+    // outputs of the samples after it
+    fn step_response<F: PoseFilter>(filter: &mut F, samples: usize) -> Vec<PoseState> {
+        filter.filter(&state(0., 0., 0.));
+        (1..samples + 1).map(|i| filter.filter(&state(i as f64 * DT, 1., 1.))).collect()
+    }
+
+    #[test]
+    fn exponential_step_reaches_63_percent_after_one_time_constant() {
+        let mut filter = ExponentialFilter::new(0.1, 0.05);
+        let out = step_response(&mut filter, 10);
+
+        let expected = 1. - (-1f32).exp();
+        assert!((yaw_of(out[9].pose.orientation) - expected).abs() < 1e-4);
+        let expected = 1. - (-2f32).exp();
+        assert!((out[9].pose.position.x - expected).abs() < 1e-4);
+    }
+
+    #[test]
+    fn exponential_without_time_constant_passes_through() {
+        let mut filter = ExponentialFilter::new(0., 0.);
+        let out = step_response(&mut filter, 1);
+        assert_eq!(out[0].pose.position.x, 1.);
+        assert!((yaw_of(out[0].pose.orientation) - 1.).abs() < 1e-6);
+    }
+
+    #[test]
+    fn filters_repeat_their_output_for_old_states() {
+        let mut filter = ExponentialFilter::new(0.1, 0.1);
+        let out = step_response(&mut filter, 3);
+        let again = filter.filter(&state(DT, 5., 0.));
+        assert_eq!(again.pose.position.x, out[2].pose.position.x);
+        assert_eq!(again.time_in_seconds, out[2].time_in_seconds);
+    }
+
+    #[test]
+    fn one_euro_without_beta_is_a_low_pass_at_min_cutoff() {
+        let still = OneEuro::new(2., 0.);
+        let mut filter = OneEuroFilter::new(still, still);
+        let out = step_response(&mut filter, 20);
+
+        // the remaining distance shrinks by 1 - alpha every sample
+        let keep = 1. - alpha(2., DT);
+        for (i, s) in out.iter().enumerate() {
+            let expected = 1. - keep.powi(i as i32 + 1);
+            assert!((s.pose.position.x - expected).abs() < 1e-5);
+            assert!((yaw_of(s.pose.orientation) - expected).abs() < 1e-4);
+        }
+    }
+
+    #[test]
+    fn one_euro_follows_fast_movement_closer() {
+        let mut slow = OneEuroFilter::new(OneEuro::new(1., 0.), OneEuro::new(1., 0.));
+        let mut fast = OneEuroFilter::new(OneEuro::new(1., 10.), OneEuro::new(1., 10.));
+        let slow = step_response(&mut slow, 10);
+        let fast = step_response(&mut fast, 10);
+
+        for (s, f) in slow.iter().zip(fast.iter()) {
+            assert!(f.pose.position.x > s.pose.position.x);
+            assert!(f.pose.position.x <= 1.);
+        }
+    }
+
+    #[test]
+    fn one_euro_smooths_jitter_at_rest() {
+        let still = OneEuro::new(1., 0.);
+        let mut filter = OneEuroFilter::new(still, still);
+        filter.filter(&state(0., 0., 0.));
+
+        // 1 mm of jitter at 50 Hz is mostly removed by a 1 Hz cutoff
+        let mut largest: f32 = 0.;
+        for i in 1..200 {
+            let jitter = if i % 2 == 0 { 0.001 } else { -0.001 };
+            let out = filter.filter(&state(i as f64 * DT, jitter, 0.));
+            if i > 100 {
+                largest = largest.max(out.pose.position.x.abs());
+            }
+        }
+        assert!(largest < 0.0001);
+    }
+
+    #[test]
+    fn kalman_variance_shrinks_at_rest() {
+        let mut filter = KalmanFilter::new(0.01, 0.0001);
+        filter.filter(&state(0., 0.5, 0.));
+
+        // the velocity starts out unknown and is learned, the position
+        // variance settles well below that of a single measurement
+        let mut last = 1.;
+        for i in 1..100 {
+            let jitter = if i % 2 == 0 { 0.01 } else { -0.01 };
+            filter.filter(&state(i as f64 * DT, 0.5 + jitter, 0.));
+            let (axes, _) = filter.axes.unwrap();
+            assert!(axes[0].p11 <= last);
+            last = axes[0].p11;
+        }
+        let (axes, _) = filter.axes.unwrap();
+        assert!(axes[0].p00 < 0.2 * 0.0001);
+        assert!(axes[0].p11 < 0.01);
+
+        // the estimate settles near the mean of the noisy measurements
+        let out = filter.filter(&state(1., 0.5, 0.));
+        assert!((out.pose.position.x - 0.5).abs() < 0.005);
+        assert!(out.linear_velocity.x.abs() < 0.1);
+    }
+
+    #[test]
+    fn kalman_estimates_constant_velocity() {
+        let mut filter = KalmanFilter::new(0.01, 0.0001);
+        let mut out = filter.filter(&state(0., 0., 0.));
+        for i in 1..200 {
+            let t = i as f64 * DT;
+            out = filter.filter(&state(t, (0.3 * t) as f32, 0.));
+        }
+        assert!((out.linear_velocity.x - 0.3).abs() < 0.01);
+        assert!((out.pose.position.x - 0.597).abs() < 0.001);
+    }
+
+    fn sensor_state(state: PoseState, flags: u32) -> SensorState {
+        SensorState {
+            predicted: state,
+            recorded: state,
+            temperature: 0.,
+            status_flags: Status { flags: flags }
+        }
+    }
+
+    #[test]
+    fn losing_tracking_resets_the_filter() {
+        let tracked = ll::Status_OrientationTracked | ll::Status_PositionConnected |
+                      ll::Status_PositionTracked;
+        let lost = ll::Status_OrientationTracked | ll::Status_PositionConnected;
+        let mut filter = ExponentialFilter::new(0.1, 0.1);
+
+        filter.filter_sensor_state(&sensor_state(state(0., 0., 0.), tracked));
+        let smoothed = filter.filter_sensor_state(&sensor_state(state(DT, 1., 0.), tracked));
+        assert!(smoothed.pose.position.x < 0.5);
+
+        // while lost the state passes through
+        let out = filter.filter_sensor_state(&sensor_state(state(2. * DT, 3., 0.), lost));
+        assert_eq!(out.pose.position.x, 3.);
+
+        // and afterwards the filter starts over instead of smoothing the jump
+        let out = filter.filter_sensor_state(&sensor_state(state(3. * DT, 5., 0.), tracked));
+        assert_eq!(out.pose.position.x, 5.);
+    }
+
+    #[test]
+    fn chain_runs_both_filters_in_order_and_resets_both() {
+        let mut chain = Chain {
+            first: KalmanFilter::new(0.01, 0.0001),
+            second: ExponentialFilter::new(0.05, 0.05)
+        };
+        let mut first = KalmanFilter::new(0.01, 0.0001);
+        let mut second = ExponentialFilter::new(0.05, 0.05);
+
+        for i in 0..10 {
+            let s = state(i as f64 * DT, i as f32 * 0.1, i as f32 * 0.05);
+            let out = chain.filter(&s);
+            let expected = second.filter(&first.filter(&s));
+            assert_eq!(out.pose.position.x, expected.pose.position.x);
+            assert_eq!(out.pose.orientation.s, expected.pose.orientation.s);
+        }
+
+        chain.reset();
+        assert!(chain.first.axes.is_none());
+        let out = chain.filter(&state(1., 7., 0.));
+        assert_eq!(out.pose.position.x, 7.);
+    }
+}
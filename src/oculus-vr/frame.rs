@@ -0,0 +1,154 @@
+// Guards for the begin/end pairs of SDK rendering. The SDK hangs or
+// misbehaves when end_frame is forgotten or an eye is ended twice, with
+// these the frame ends when its guard is dropped and every eye can only be
+// begun once and ended once.
+//
+//     let mut frame = hmd.frame(frame_index);
+//     while let Some(eye) = frame.next_eye() {
+//         render(eye.eye(), eye.pose());
+//         eye.finish(&texture);
+//     }
+//     // end_frame is called here
+//
+// The frame borrows the hmd mutably, so there is only ever one frame and
+// the SDK calls can not be made around it. An eye that is dropped without
+// finish, by an early return or a panic while rendering it, is ended with
+// an empty texture before the next eye begins or the frame ends, so the SDK
+// always sees complete pairs. The distortion then shows nothing for that
+// eye in this frame.
+
+use std::default::Default;
+
+use super::{ll, Eye, FrameTiming, Pose, RenderingHmd, Texture, ToTexture};
+
+pub struct Frame<'b, 'a: 'b> {
+    hmd: &'b RenderingHmd<'a>,
+    timing: FrameTiming,
+    order: [Eye; 2],
+    // the pose each eye of order was begun with
+    begun: [Option<Pose>; 2],
+    // whether each eye of order was ended
+    ended: [bool; 2]
+}
+
+impl<'a> RenderingHmd<'a> {
+    // begins a frame, it is ended when the returned guard is dropped
+    pub fn frame<'b>(&'b mut self, frame_index: usize) -> Frame<'b, 'a> {
+        let order = unsafe {
+            let mut desc: ll::HmdDesc = Default::default();
+            ll::ovrHmd_GetDesc(self.ptr, &mut desc);
+            [Eye::from_ll(desc.eye_render_order[0]), Eye::from_ll(desc.eye_render_order[1])]
+        };
+
+        Frame {
+            timing: self.begin_frame(frame_index),
+            hmd: self,
+            order: order,
+            begun: [None, None],
+            ended: [false, false]
+        }
+    }
+}
+
+impl<'b, 'a> Frame<'b, 'a> {
+    pub fn timing(&self) -> &FrameTiming {
+        &self.timing
+    }
+
+    // the order the hmd wants the eyes rendered in, next_eye follows it
+    pub fn eye_render_order(&self) -> [Eye; 2] {
+        self.order
+    }
+
+    // begins the next eye in render order, None once both were begun
+    pub fn next_eye<'f>(&'f mut self) -> Option<EyeRender<'f, 'b, 'a>> {
+        match self.begun.iter().position(|begun| begun.is_none()) {
+            Some(i) => Some(self.begin(i)),
+            None => None
+        }
+    }
+
+    // begins a specific eye, None when it was already begun this frame
+    pub fn eye<'f>(&'f mut self, eye: Eye) -> Option<EyeRender<'f, 'b, 'a>> {
+        let i = match (self.order[0], eye) {
+            (Eye::Left, Eye::Left) | (Eye::Right, Eye::Right) => 0,
+            _ => 1
+        };
+        if self.begun[i].is_some() {
+            None
+        } else {
+            Some(self.begin(i))
+        }
+    }
+
+    fn begin<'f>(&'f mut self, i: usize) -> EyeRender<'f, 'b, 'a> {
+        self.end_unfinished();
+        let eye = self.order[i];
+        let pose = self.hmd.begin_eye_render(eye);
+        self.begun[i] = Some(pose);
+        EyeRender {
+            frame: self,
+            index: i,
+            eye: eye,
+            pose: pose
+        }
+    }
+
+    // ends an eye that was begun but never finished with an empty texture
+    fn end_unfinished(&mut self) {
+        for i in 0..2 {
+            if let Some(pose) = self.begun[i] {
+                if !self.ended[i] {
+                    let empty = Texture {
+                        size: Default::default(),
+                        viewport: Default::default(),
+                        texture: 0
+                    };
+                    self.hmd.end_eye_render(self.order[i], pose, &empty);
+                    self.ended[i] = true;
+                }
+            }
+        }
+    }
+
+    // ends the frame now, the same as dropping it
+    pub fn end(self) {}
+}
+
+impl<'b, 'a> Drop for Frame<'b, 'a> {
+    fn drop(&mut self) {
+        self.end_unfinished();
+        self.hmd.end_frame();
+    }
+}
+
+// An eye being rendered, finish ends it with the texture it was rendered
+// into. The frame can not begin another eye or end while it is alive, when
+// it is dropped without finish the frame ends it with an empty texture.
+#[must_use = "an eye has to be finished with the texture it was rendered into"]
+pub struct EyeRender<'f, 'b: 'f, 'a: 'b> {
+    frame: &'f mut Frame<'b, 'a>,
+    index: usize,
+    eye: Eye,
+    pose: Pose
+}
+
+impl<'f, 'b, 'a> EyeRender<'f, 'b, 'a> {
+    pub fn eye(&self) -> Eye {
+        self.eye
+    }
+
+    // the pose the eye is rendered with, as returned by begin_eye_render
+    pub fn pose(&self) -> Pose {
+        self.pose
+    }
+
+    pub fn timing(&self) -> &FrameTiming {
+        &self.frame.timing
+    }
+
+    pub fn finish<T: ToTexture>(self, texture: &T) {
+        self.frame.hmd.end_eye_render(self.eye, self.pose, texture);
+        self.frame.ended[self.index] = true;
+    }
+}
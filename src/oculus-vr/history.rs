@@ -0,0 +1,182 @@
+// A ring buffer of past pose states, for asking where the head was or will
+// be at a given time rather than only predicting forward from now.
+//
+// Queries between two samples interpolate them, queries outside of the
+// buffer predict from the nearest sample, by default using its velocities
+// and accelerations, but never further than max_extrapolation seconds.
+
+use std::collections::VecDeque;
+use std::collections::vec_deque;
+
+use super::{PoseState, SensorState, FrameTiming, Eye, Predictor};
+
+pub struct PoseHistory {
+    samples: VecDeque<PoseState>,
+    capacity: usize,
+    max_extrapolation: f64,
+    predictor: Predictor
+}
+
+impl PoseHistory {
+    // keeps the newest capacity samples and extrapolates up to 100ms
+    pub fn new(capacity: usize) -> PoseHistory {
+        PoseHistory {
+            samples: VecDeque::with_capacity(capacity),
+            capacity: capacity,
+            max_extrapolation: 0.1,
+            predictor: Predictor::ConstantAcceleration
+        }
+    }
+
+    pub fn max_extrapolation(mut self, seconds: f64) -> PoseHistory {
+        self.max_extrapolation = seconds; self
+    }
+
+    // how states outside of the buffer are extrapolated
+    pub fn predictor(mut self, predictor: Predictor) -> PoseHistory {
+        self.predictor = predictor; self
+    }
+
+    // adds a sample, samples that are not newer than the newest one or
+    // whose time is not finite are ignored and false is returned
+    pub fn push(&mut self, state: PoseState) -> bool {
+        if !state.time_in_seconds.is_finite() {
+            return false;
+        }
+        match self.samples.back() {
+            Some(newest) if state.time_in_seconds <= newest.time_in_seconds => return false,
+            _ => ()
+        }
+        if self.capacity == 0 {
+            return false;
+        }
+        if self.samples.len() == self.capacity {
+            self.samples.pop_front();
+        }
+        self.samples.push_back(state);
+        true
+    }
+
+    // adds the recorded pose state of a sensor state
+    pub fn push_sensor_state(&mut self, state: &SensorState) -> bool {
+        self.push(state.recorded)
+    }
+
+    pub fn len(&self) -> usize {
+        self.samples.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.samples.is_empty()
+    }
+
+    pub fn clear(&mut self) {
+        self.samples.clear();
+    }
+
+    pub fn oldest(&self) -> Option<&PoseState> {
+        self.samples.front()
+    }
+
+    pub fn newest(&self) -> Option<&PoseState> {
+        self.samples.back()
+    }
+
+    // the samples, oldest first
+    pub fn iter(&self) -> vec_deque::Iter<PoseState> {
+        self.samples.iter()
+    }
+
+    // the pose state at time. The returned time_in_seconds is the time the
+    // state is for, which differs from the requested one when the
+    // extrapolation was cut off at max_extrapolation. None without samples
+    // or for a time that is not finite.
+    pub fn pose_at(&self, time: f64) -> Option<PoseState> {
+        let n = self.samples.len();
+        if n == 0 || !time.is_finite() {
+            return None;
+        }
+
+        let newest = &self.samples[n - 1];
+        if time >= newest.time_in_seconds {
+            let dt = (time - newest.time_in_seconds).min(self.max_extrapolation);
+            return Some(newest.predict_with(self.predictor, dt));
+        }
+
+        let oldest = &self.samples[0];
+        if time < oldest.time_in_seconds {
+            let dt = (time - oldest.time_in_seconds).max(-self.max_extrapolation);
+            return Some(oldest.predict_with(self.predictor, dt));
+        }
+
+        // queries are usually about the last few samples, search from the back
+        let mut i = n - 1;
+        while self.samples[i - 1].time_in_seconds > time {
+            i -= 1;
+        }
+        let a = &self.samples[i - 1];
+        let b = &self.samples[i];
+        let amount = (time - a.time_in_seconds) / (b.time_in_seconds - a.time_in_seconds);
+        Some(a.lerp(b, amount as f32))
+    }
+
+    // the pose state when the given eye is scanned out in the frame
+    pub fn pose_at_scanout(&self, timing: &FrameTiming, eye: Eye) -> Option<PoseState> {
+        self.pose_at(*timing.eye_scanout_seconds.eye(eye))
+    }
+
+    // the pose state halfway through scanning out the frame
+    pub fn pose_at_scanout_midpoint(&self, timing: &FrameTiming) -> Option<PoseState> {
+        self.pose_at(timing.scanout_midpoint_seconds)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::f64;
+
+    use cgmath::{Quaternion, Vector3};
+
+    use super::PoseHistory;
+    use super::super::{Pose, PoseState};
+
+    fn pose_state(time: f64, x: f32) -> PoseState {
+        let zero = Vector3::new(0., 0., 0.);
+        PoseState {
+            pose: Pose {
+                orientation: Quaternion::new(1., 0., 0., 0.),
+                position: Vector3::new(x, 0., 0.)
+            },
+            angular_velocity: zero,
+            linear_velocity: zero,
+            angular_acceleration: zero,
+            linear_acceleration: zero,
+            time_in_seconds: time
+        }
+    }
+
+    #[test]
+    fn times_that_are_not_finite_have_no_pose() {
+        let mut history = PoseHistory::new(4);
+        history.push(pose_state(1., 0.));
+        assert!(history.pose_at(f64::NAN).is_none());
+        assert!(history.pose_at(f64::INFINITY).is_none());
+
+        history.push(pose_state(2., 1.));
+        assert!(history.pose_at(f64::NAN).is_none());
+        assert_eq!(history.pose_at(1.5).unwrap().pose.position, Vector3::new(0.5, 0., 0.));
+    }
+
+    #[test]
+    fn samples_that_are_not_finite_are_ignored() {
+        let mut history = PoseHistory::new(4);
+        assert!(!history.push(pose_state(f64::NAN, 0.)));
+        assert!(history.push(pose_state(1., 0.)));
+        assert!(!history.push(pose_state(f64::INFINITY, 5.)));
+        assert!(!history.push(pose_state(f64::NAN, 5.)));
+        assert!(history.push(pose_state(2., 1.)));
+
+        assert_eq!(history.len(), 2);
+        assert_eq!(history.pose_at(1.5).unwrap().pose.position, Vector3::new(0.5, 0., 0.));
+    }
+}
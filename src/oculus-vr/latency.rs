@@ -0,0 +1,181 @@
+// The SDK's latency testing, both the external DK1 latency tester that
+// watches a patch of the screen and the DK2's continuous measurement that
+// reads back pixels. Parsing the tester's result needs no SDK, so only the
+// tester itself is behind the sdk feature.
+
+use std::str::FromStr;
+
+#[cfg(feature = "sdk")]
+use libc::c_uchar;
+
+#[cfg(feature = "sdk")]
+use super::{ll, from_buf, Hmd};
+
+// min, average and max of a timing in milliseconds
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct LatencyRange {
+    pub min: f32,
+    pub average: f32,
+    pub max: f32
+}
+
+// the numbers of a finished latency tester run, in milliseconds
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct LatencyMeasurement {
+    // the tester's estimate of motion to photon latency, without half of
+    // the tracker's period
+    pub total: f32,
+    pub black_to_white: LatencyRange,
+    pub white_to_black: LatencyRange,
+    pub usb_round_trip: LatencyRange,
+    pub samples: u32,
+    pub timeouts: u32
+}
+
+#[derive(Debug, Clone)]
+pub struct LatencyResult {
+    // the string the SDK reported
+    pub text: String,
+    // the numbers in text, None when it was not in the format of the
+    // 0.3.2 SDK
+    pub measurement: Option<LatencyMeasurement>
+}
+
+// the numbers in s in order, a - only counts as a sign when a digit follows
+fn numbers(s: &str) -> Vec<f32> {
+    let mut found = Vec::new();
+    let mut current = String::new();
+    let mut chars = s.chars().peekable();
+    while let Some(c) = chars.next() {
+        let sign = c == '-' && current.len() == 0 &&
+            chars.peek().map_or(false, |n| n.is_digit(10));
+        if c.is_digit(10) || c == '.' || sign {
+            current.push(c);
+        } else if current.len() > 0 {
+            if let Ok(n) = f32::from_str(&current) {
+                found.push(n);
+            }
+            current.clear();
+        }
+    }
+    if let Ok(n) = f32::from_str(&current) {
+        found.push(n);
+    }
+    found
+}
+
+impl LatencyMeasurement {
+    // parses the tester's result, which Util_LatencyTest.cpp formats with
+    // "RESULT=%.1f (add half Tracker period) [b->w %d|%.1f|%d] [w->b %d|%.1f|%d]
+    //     [usb rndtrp %.1f|%.1f|%.1f] [cnt %d] [tmouts %d]"
+    pub fn parse(text: &str) -> Option<LatencyMeasurement> {
+        if !text.starts_with("RESULT=") {
+            return None;
+        }
+        let n = numbers(text);
+        if n.len() != 12 {
+            return None;
+        }
+        let range = |i: usize| LatencyRange { min: n[i], average: n[i + 1], max: n[i + 2] };
+
+        Some(LatencyMeasurement {
+            total: n[0],
+            black_to_white: range(1),
+            white_to_black: range(4),
+            usb_round_trip: range(7),
+            samples: n[10] as u32,
+            timeouts: n[11] as u32
+        })
+    }
+}
+
+// Runs the latency tests of an hmd, borrowed from Hmd::latency_tester.
+// process has to be called once every frame while a test runs.
+#[cfg(feature = "sdk")]
+pub struct LatencyTester<'b, 'a: 'b> {
+    hmd: &'b Hmd<'a>
+}
+
+#[cfg(feature = "sdk")]
+impl<'a> Hmd<'a> {
+    pub fn latency_tester<'b>(&'b self) -> LatencyTester<'b, 'a> {
+        LatencyTester { hmd: self }
+    }
+}
+
+#[cfg(feature = "sdk")]
+impl<'b, 'a> LatencyTester<'b, 'a> {
+    // advances a running test, Some is the color to clear the screen with
+    // this frame so the tester can see it
+    pub fn process(&self) -> Option<[u8; 3]> {
+        let mut rgb: [c_uchar; 3] = [0; 3];
+        unsafe {
+            if ll::ovrHmd_ProcessLatencyTest(self.hmd.ptr, &mut rgb[0]) {
+                Some([rgb[0] as u8, rgb[1] as u8, rgb[2] as u8])
+            } else {
+                None
+            }
+        }
+    }
+
+    // the result of a finished test, each result is only returned once
+    pub fn result(&self) -> Option<LatencyResult> {
+        unsafe {
+            let text = ll::ovrHmd_GetLatencyTestResult(self.hmd.ptr);
+            if text.is_null() {
+                return None;
+            }
+            let text = from_buf(text as *const u8);
+            Some(LatencyResult {
+                measurement: LatencyMeasurement::parse(&text),
+                text: text
+            })
+        }
+    }
+
+    // the DK2's continuously measured latency in seconds, needs the
+    // latency_test hmd capability. This is the third of the SDK's
+    // GetLatencyTimings, the median time from the end of a frame to the
+    // hmd scanning out its readback pixels, not the render or timewarp
+    // latency of the first two. None when the hmd can not measure it or
+    // has no measurement from the last 2 seconds.
+    pub fn measured_latency(&self) -> Option<f64> {
+        let latency = unsafe { ll::ovrHmd_GetMeasuredLatencyTest2(self.hmd.ptr) };
+        if latency > 0. {
+            Some(latency as f64)
+        } else {
+            None
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{LatencyMeasurement, LatencyRange};
+
+    // a result as printed by the format string in Util_LatencyTest.cpp
+    const RESULT: &'static str = "RESULT=54.2 (add half Tracker period) \
+        [b->w 50|51.3|53] [w->b 52|53.0|55] [usb rndtrp 1.0|1.1|1.3] [cnt 20] [tmouts 2]";
+
+    #[test]
+    fn parses_the_testers_result() {
+        let m = LatencyMeasurement::parse(RESULT).unwrap();
+        assert_eq!(m, LatencyMeasurement {
+            total: 54.2,
+            black_to_white: LatencyRange { min: 50., average: 51.3, max: 53. },
+            white_to_black: LatencyRange { min: 52., average: 53., max: 55. },
+            usb_round_trip: LatencyRange { min: 1., average: 1.1, max: 1.3 },
+            samples: 20,
+            timeouts: 2
+        });
+    }
+
+    #[test]
+    fn rejects_other_formats() {
+        assert_eq!(LatencyMeasurement::parse(""), None);
+        assert_eq!(LatencyMeasurement::parse(&RESULT[1..]), None);
+        assert_eq!(LatencyMeasurement::parse("RESULT=54.2 [cnt 20] [tmouts 0]"), None);
+        let extra = format!("{} [late 3]", RESULT);
+        assert_eq!(LatencyMeasurement::parse(&extra), None);
+    }
+}
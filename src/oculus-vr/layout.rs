@@ -0,0 +1,261 @@
+// Planning of the render targets the eyes are rendered into, either one
+// texture shared side by side or a texture for each eye, with the viewports
+// and the Texture descriptors the eyes of a frame are finished with.
+
+use super::{ll, Eye, FovPort, HmdDescription, PerEye, Texture};
+
+// anything that knows how large a texture has to be to render an eye's fov
+// at a pixel density, a real Hmd or a SimulatedHmd
+pub trait FovTextureSize {
+    fn get_fov_texture_size(&self, eye: Eye, fov: FovPort, pixels_per_display_pixel: f32) -> ll::Sizei;
+}
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum Layout {
+    // one texture, the left eye on the left half and the right on the right
+    Shared,
+    // a texture for each eye
+    Separate
+}
+
+pub struct RenderTargetPlanner {
+    layout: Layout,
+    pixel_density: f32,
+    padding: i32,
+    alignment: i32
+}
+
+impl RenderTargetPlanner {
+    // a shared texture at a pixel density of 1, without padding or alignment
+    pub fn new() -> RenderTargetPlanner {
+        RenderTargetPlanner {
+            layout: Layout::Shared,
+            pixel_density: 1.,
+            padding: 0,
+            alignment: 1
+        }
+    }
+
+    pub fn layout(mut self, layout: Layout) -> RenderTargetPlanner {
+        self.layout = layout; self
+    }
+
+    // rendered pixels per display pixel at the center of the fov
+    pub fn pixel_density(mut self, pixel_density: f32) -> RenderTargetPlanner {
+        self.pixel_density = pixel_density; self
+    }
+
+    // pixels left empty around every viewport, so filtering near the edge
+    // of one eye does not pick up the other, negative padding is none
+    pub fn padding(mut self, pixels: i32) -> RenderTargetPlanner {
+        self.padding = pixels.max(0); self
+    }
+
+    // texture sizes are rounded up to a multiple of this, anything below 1
+    // does not align
+    pub fn alignment(mut self, pixels: i32) -> RenderTargetPlanner {
+        self.alignment = pixels.max(1); self
+    }
+
+    // plans the targets for rendering fovs, each is first clamped to what
+    // the eye can see
+    pub fn plan<S: FovTextureSize>(&self, hmd: &S, desc: &HmdDescription,
+                                   fovs: &PerEye<FovPort>) -> RenderTargetPlan {
+        let fovs = fovs.map(|eye, fov| desc.eye_fovs.eye(eye).clamp_fov(fov));
+        let sizes = fovs.map(|eye, fov| hmd.get_fov_texture_size(eye, *fov, self.pixel_density));
+        let pad = self.padding;
+
+        let rect = |x: i32, size: ll::Sizei| ll::Recti {
+            pos: ll::Vector2i { x: x, y: pad },
+            size: size
+        };
+
+        match self.layout {
+            Layout::Shared => {
+                let width = pad + sizes.left.x + pad + sizes.right.x + pad;
+                let height = pad + sizes.left.y.max(sizes.right.y) + pad;
+                RenderTargetPlan {
+                    layout: Layout::Shared,
+                    fovs: fovs,
+                    texture_sizes: vec![self.align(width, height)],
+                    viewports: PerEye::new(rect(pad, sizes.left),
+                                           rect(pad + sizes.left.x + pad, sizes.right)),
+                    texture_index: PerEye::new(0, 0)
+                }
+            }
+            Layout::Separate => {
+                let texture = |size: ll::Sizei| self.align(pad + size.x + pad, pad + size.y + pad);
+                RenderTargetPlan {
+                    layout: Layout::Separate,
+                    fovs: fovs,
+                    texture_sizes: vec![texture(sizes.left), texture(sizes.right)],
+                    viewports: PerEye::new(rect(pad, sizes.left), rect(pad, sizes.right)),
+                    texture_index: PerEye::new(0, 1)
+                }
+            }
+        }
+    }
+
+    fn align(&self, width: i32, height: i32) -> ll::Sizei {
+        let a = self.alignment;
+        ll::Sizei {
+            x: (width + a - 1) / a * a,
+            y: (height + a - 1) / a * a
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct RenderTargetPlan {
+    pub layout: Layout,
+    // the fovs the plan is for, pass these to configure_rendering
+    pub fovs: PerEye<FovPort>,
+    // one size for a shared layout, left and right for separate ones
+    pub texture_sizes: Vec<ll::Sizei>,
+    // where each eye is rendered in its texture
+    pub viewports: PerEye<ll::Recti>,
+    // which of texture_sizes each eye renders into
+    pub texture_index: PerEye<usize>
+}
+
+impl RenderTargetPlan {
+    pub fn texture_size(&self, eye: Eye) -> ll::Sizei {
+        self.texture_sizes[*self.texture_index.eye(eye)]
+    }
+
+    // the descriptor to finish an eye with, opengl_texture is the
+    // texture the eye was rendered into
+    pub fn texture(&self, eye: Eye, opengl_texture: u32) -> Texture {
+        Texture {
+            size: self.texture_size(eye),
+            viewport: *self.viewports.eye(eye),
+            texture: opengl_texture
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::cell::Cell;
+
+    use super::{FovTextureSize, Layout, RenderTargetPlanner};
+    use super::super::{ll, Eye, FovPort, HmdType, PerEye};
+    use super::super::simulated::{SimulatedHmd, Stationary};
+
+    // fixed texture sizes for each eye, whatever the fov
+    struct Sizes {
+        left: ll::Sizei,
+        right: ll::Sizei,
+        density: Cell<f32>
+    }
+
+    impl FovTextureSize for Sizes {
+        fn get_fov_texture_size(&self, eye: Eye, _: FovPort, pixels_per_display_pixel: f32) -> ll::Sizei {
+            self.density.set(pixels_per_display_pixel);
+            match eye {
+                Eye::Left => self.left,
+                Eye::Right => self.right
+            }
+        }
+    }
+
+    fn sizes() -> Sizes {
+        Sizes {
+            left: ll::Sizei { x: 100, y: 90 },
+            right: ll::Sizei { x: 101, y: 95 },
+            density: Cell::new(0.)
+        }
+    }
+
+    fn fovs() -> PerEye<FovPort> {
+        let fov = FovPort { up: 1., down: 1., left: 1., right: 1. };
+        PerEye::new(fov, fov)
+    }
+
+    fn assert_rect(rect: ll::Recti, x: i32, y: i32, width: i32, height: i32) {
+        assert_eq!((rect.pos.x, rect.pos.y, rect.size.x, rect.size.y), (x, y, width, height));
+    }
+
+    #[test]
+    fn shared_puts_the_eyes_side_by_side() {
+        let hmd = sizes();
+        let desc = SimulatedHmd::new(HmdType::DK1, Stationary::new()).get_description();
+        let plan = RenderTargetPlanner::new()
+            .pixel_density(1.5)
+            .padding(3)
+            .alignment(12)
+            .plan(&hmd, &desc, &fovs());
+
+        assert_eq!(hmd.density.get(), 1.5);
+        assert_eq!(plan.layout, Layout::Shared);
+        assert_eq!(plan.texture_sizes.len(), 1);
+        // 3 + 100 + 3 + 101 + 3 = 210 and 3 + 95 + 3 = 101, rounded up to 12
+        assert_eq!((plan.texture_sizes[0].x, plan.texture_sizes[0].y), (216, 108));
+        assert_rect(plan.viewports.left, 3, 3, 100, 90);
+        assert_rect(plan.viewports.right, 106, 3, 101, 95);
+
+        let texture = plan.texture(Eye::Right, 7);
+        assert_eq!(texture.texture, 7);
+        assert_eq!((texture.size.x, texture.size.y), (216, 108));
+        assert_rect(texture.viewport, 106, 3, 101, 95);
+    }
+
+    #[test]
+    fn separate_gives_each_eye_its_own_texture() {
+        let desc = SimulatedHmd::new(HmdType::DK1, Stationary::new()).get_description();
+        let plan = RenderTargetPlanner::new()
+            .layout(Layout::Separate)
+            .padding(3)
+            .alignment(12)
+            .plan(&sizes(), &desc, &fovs());
+
+        assert_eq!(plan.texture_sizes.len(), 2);
+        // 106 x 96 and 107 x 101 rounded up to 12
+        let left = plan.texture_size(Eye::Left);
+        let right = plan.texture_size(Eye::Right);
+        assert_eq!((left.x, left.y), (108, 96));
+        assert_eq!((right.x, right.y), (108, 108));
+        assert_rect(plan.viewports.left, 3, 3, 100, 90);
+        assert_rect(plan.viewports.right, 3, 3, 101, 95);
+        assert_eq!((plan.texture_index.left, plan.texture_index.right), (0, 1));
+    }
+
+    #[test]
+    fn defaults_neither_pad_nor_align() {
+        let desc = SimulatedHmd::new(HmdType::DK1, Stationary::new()).get_description();
+        let plan = RenderTargetPlanner::new()
+            .padding(-4)
+            .alignment(0)
+            .plan(&sizes(), &desc, &fovs());
+
+        assert_eq!((plan.texture_sizes[0].x, plan.texture_sizes[0].y), (201, 95));
+        assert_rect(plan.viewports.left, 0, 0, 100, 90);
+        assert_rect(plan.viewports.right, 100, 0, 101, 95);
+    }
+
+    #[test]
+    fn fovs_are_clamped_to_what_the_eye_can_see() {
+        let desc = SimulatedHmd::new(HmdType::DK1, Stationary::new()).get_description();
+        let wide = FovPort { up: 5., down: 5., left: 5., right: 5. };
+        let plan = RenderTargetPlanner::new()
+            .plan(&sizes(), &desc, &PerEye::new(wide, wide));
+
+        for &eye in [Eye::Left, Eye::Right].iter() {
+            let fov = plan.fovs.eye(eye);
+            let max = desc.eye_fovs.eye(eye).max_eye_fov;
+            assert_eq!((fov.up, fov.down, fov.left, fov.right), (max.up, max.down, max.left, max.right));
+        }
+    }
+
+    #[test]
+    fn plans_for_a_simulated_hmd() {
+        let hmd = SimulatedHmd::new(HmdType::DK2, Stationary::new());
+        let desc = hmd.get_description();
+        let fovs = PerEye::new(desc.eye_fovs.left.default_eye_fov, desc.eye_fovs.right.default_eye_fov);
+        let plan = RenderTargetPlanner::new().plan(&hmd, &desc, &fovs);
+
+        let left = hmd.get_fov_texture_size(Eye::Left, fovs.left, 1.);
+        assert_rect(plan.viewports.left, 0, 0, left.x, left.y);
+        assert_eq!(plan.viewports.right.pos.x, left.x);
+    }
+}
@@ -2,20 +2,46 @@
 #![crate_type = "lib"]
 #![feature(link_args, path, core, std_misc)]
 #![allow(non_upper_case_globals)]
+// the conversions from and to the SDK's types are only used with the SDK
+#![cfg_attr(not(feature = "sdk"), allow(dead_code))]
 
 extern crate cgmath;
 extern crate libc;
-
-use libc::{c_int, c_uint, c_void, c_float, c_double};
+extern crate time;
+extern crate vr_common;
+#[cfg(feature = "steamvr")]
+extern crate steamworks_vr;
+
+use libc::{c_int, c_uint, c_void, c_float};
+#[cfg(feature = "sdk")]
+use libc::c_double;
+#[cfg(feature = "sdk")]
 use std::default::Default;
+use std::error;
+use std::fmt;
+#[cfg(feature = "sdk")]
+use std::marker::PhantomData;
+#[cfg(feature = "sdk")]
+use std::ops::Deref;
 use std::ptr;
+#[cfg(feature = "sdk")]
+use std::slice;
+#[cfg(feature = "sdk")]
+use std::sync::atomic::{AtomicBool, ATOMIC_BOOL_INIT, Ordering};
 use std::old_path::BytesContainer;
 
 use cgmath::Quaternion;
 use cgmath::{Vector2, Vector3};
 use cgmath::{Matrix4};
 
-#[cfg(target_os = "linux")]
+use vr_common::Viewport;
+#[cfg(feature = "sdk")]
+use vr_common::DisplayDescription;
+pub use vr_common::HeadMountedDisplay;
+pub use prediction::Predictor;
+pub use projection::{Projection, DepthRange};
+
+#[cfg(all(feature = "sdk", target_os = "linux"))]
 #[link(name="ovr")]
 #[link(name="stdc++")]
 #[link(name="udev")]
@@ -25,7 +51,7 @@ use cgmath::{Matrix4};
 #[link(name="GL")]
 extern {}
 
-#[cfg(target_os = "macos")]
+#[cfg(all(feature = "sdk", target_os = "macos"))]
 #[link(name="ovr")]
 #[link(name="stdc++")]
 #[link(name = "Cocoa", kind = "framework")]
@@ -33,9 +59,31 @@ extern {}
 #[link(name = "CoreFoundation", kind = "framework")]
 extern {}
 
+mod math;
+pub mod simulated;
+pub mod recording;
+pub mod poller;
+pub mod history;
+pub mod prediction;
+pub mod filter;
+pub mod projection;
+pub mod layout;
+pub mod properties;
+pub mod profile;
+pub mod latency;
+#[cfg(feature = "sdk")]
+pub mod frame;
+
+// the SteamVR bindings, so one dependency gives an engine every runtime
+#[cfg(feature = "steamvr")]
+pub mod steamvr {
+    pub use steamworks_vr::*;
+}
 
 pub mod ll {
-    use libc::{c_uint, c_int, c_float, c_char, c_void, c_double, c_short};
+    use libc::{c_uint, c_int, c_float, c_char, c_void, c_double, c_short, c_ushort};
+    #[cfg(feature = "sdk")]
+    use libc::c_uchar;
     use std::ptr;
     use std::default::Default;
 
@@ -221,6 +269,36 @@ pub mod ll {
         pub padd: [*const c_void; 7]
     }
 
+    #[derive(Copy, Default, Debug, Clone)]
+    #[repr(C)]
+    pub struct DistortionVertex {
+        pub screen_position: Vector2f,
+        pub timewarp_factor: c_float,
+        pub vignette_factor: c_float,
+        pub tex_red: Vector2f,
+        pub tex_green: Vector2f,
+        pub tex_blue: Vector2f
+    }
+
+    #[repr(C)]
+    pub struct DistortionMesh {
+        pub vertex_data: *mut DistortionVertex,
+        pub index_data: *mut c_ushort,
+        pub vertex_count: c_uint,
+        pub index_count: c_uint
+    }
+
+    impl Default for DistortionMesh {
+        fn default() -> DistortionMesh {
+            DistortionMesh {
+                vertex_data: ptr::null_mut(),
+                index_data: ptr::null_mut(),
+                vertex_count: 0,
+                index_count: 0
+            }
+        }
+    }
+
     pub const Hmd_None                      : c_int = 0;
     pub const Hmd_DK1                       : c_int = 3;
     pub const Hmd_DKHD                      : c_int = 4;
@@ -261,6 +339,7 @@ pub mod ll {
     pub const RenderAPI_D3D11               : c_uint = 5;
     pub const RenderAPI_Count               : c_uint = 6;
 
+    #[cfg(feature = "sdk")]
     extern "C" {
         pub fn ovr_Initialize() -> bool;
         pub fn ovr_Shutdown();
@@ -297,10 +376,63 @@ pub mod ll {
         pub fn ovrHmd_BeginEyeRender(hmd: *mut Hmd, eye: c_uint) -> Posef;
         pub fn ovrHmd_EndEyeRender(hmd: *mut Hmd, eye: c_uint, 
                                    pose: Posef, texture: *const Texture);
+        pub fn ovrHmd_CreateDistortionMesh(hmd: *mut Hmd,
+                                           eye: c_uint,
+                                           fov: FovPort,
+                                           distortion_caps: c_uint,
+                                           mesh_data: *mut DistortionMesh) -> bool;
+        pub fn ovrHmd_DestroyDistortionMesh(mesh_data: *mut DistortionMesh);
+        pub fn ovrHmd_GetRenderDesc(hmd: *mut Hmd,
+                                    eye: c_uint,
+                                    fov: FovPort) -> EyeRenderDesc;
+        pub fn ovrHmd_GetRenderScaleAndOffset(fov: FovPort,
+                                              texture_size: Sizei,
+                                              render_viewport: Recti,
+                                              uv_scale_offset_out: *mut Vector2f);
+        pub fn ovrHmd_GetFrameTiming(hmd: *mut Hmd,
+                                     frame_index: c_uint) -> FrameTiming;
+        pub fn ovrHmd_BeginFrameTiming(hmd: *mut Hmd,
+                                       frame_index: c_uint) -> FrameTiming;
+        pub fn ovrHmd_EndFrameTiming(hmd: *mut Hmd);
+        pub fn ovrHmd_ResetFrameTiming(hmd: *mut Hmd, frame_index: c_uint);
+        pub fn ovrHmd_GetEyePose(hmd: *mut Hmd, eye: c_uint) -> Posef;
+        pub fn ovrHmd_GetEyeTimewarpMatrices(hmd: *mut Hmd,
+                                             eye: c_uint,
+                                             render_pose: Posef,
+                                             twm_out: *mut Matrix4f);
         pub fn ovrMatrix4f_Projection(fov: FovPort,
                                       znear: c_float,
                                       zfar: c_float,
                                       right_handed: bool) -> Matrix4f;
+        pub fn ovrMatrix4f_OrthoSubProjection(projection: Matrix4f,
+                                              ortho_scale: Vector2f,
+                                              ortho_distance: c_float,
+                                              eye_view_adjust_x: c_float) -> Matrix4f;
+
+        pub fn ovrHmd_GetFloat(hmd: *mut Hmd,
+                               property_name: *const c_char,
+                               default_val: c_float) -> c_float;
+        pub fn ovrHmd_SetFloat(hmd: *mut Hmd,
+                               property_name: *const c_char,
+                               value: c_float) -> bool;
+        pub fn ovrHmd_GetFloatArray(hmd: *mut Hmd,
+                                    property_name: *const c_char,
+                                    values: *mut c_float,
+                                    array_size: c_uint) -> c_uint;
+        pub fn ovrHmd_SetFloatArray(hmd: *mut Hmd,
+                                    property_name: *const c_char,
+                                    values: *mut c_float,
+                                    array_size: c_uint) -> bool;
+        pub fn ovrHmd_GetString(hmd: *mut Hmd,
+                                property_name: *const c_char,
+                                default_val: *const c_char) -> *const c_char;
+        pub fn ovrHmd_GetArraySize(hmd: *mut Hmd,
+                                   property_name: *const c_char) -> c_uint;
+
+        pub fn ovrHmd_ProcessLatencyTest(hmd: *mut Hmd,
+                                         rgb_color_out: *mut c_uchar) -> bool;
+        pub fn ovrHmd_GetLatencyTestResult(hmd: *mut Hmd) -> *const c_char;
+        pub fn ovrHmd_GetMeasuredLatencyTest2(hmd: *mut Hmd) -> c_double;
 
         pub fn ovr_WaitTillTime(abs_time: c_double) -> c_double;
         pub fn ovr_GetTimeInSeconds() -> c_double;
@@ -308,14 +440,52 @@ pub mod ll {
 }
 
 
+#[cfg(feature = "sdk")]
 pub fn get_time() -> f64 {
     unsafe{ ll::ovr_GetTimeInSeconds() as f64 }
 }
 
+#[cfg(feature = "sdk")]
 pub fn wait_till_time(time: f64) -> f64 {
     unsafe{ ll::ovr_WaitTillTime(time as c_double) as f64 }
 }
 
+// returns the (scale, offset) pair used to map tan angles to texture uv
+// coordinates in the distortion shader
+#[cfg(feature = "sdk")]
+pub fn get_render_scale_and_offset(fov: FovPort,
+                                   texture_size: ll::Sizei,
+                                   render_viewport: ll::Recti) -> (Vector2<f32>, Vector2<f32>) {
+    unsafe {
+        let mut out: [ll::Vector2f; 2] = [Default::default(); 2];
+        ll::ovrHmd_GetRenderScaleAndOffset(fov.to_ll(),
+                                           texture_size,
+                                           render_viewport,
+                                           &mut out[0]);
+        (Vector2::new(out[0].x, out[0].y),
+         Vector2::new(out[1].x, out[1].y))
+    }
+}
+
+// the projection for 2D elements drawn ortho_distance meters in front of
+// an eye, e.g. a HUD. ortho_scale is the size of a pixel in tan angle units
+// and eye_view_adjust_x the x of the eye's view_adjust, so both eyes see
+// the layer at the same depth. The y axis points down like in 2D
+// rendering, with the origin at the center of the eye's fov.
+#[cfg(feature = "sdk")]
+pub fn ortho_sub_projection(projection: &Matrix4<f32>,
+                            ortho_scale: Vector2<f32>,
+                            ortho_distance: f32,
+                            eye_view_adjust_x: f32) -> Matrix4<f32> {
+    let scale = ll::Vector2f { x: ortho_scale.x, y: ortho_scale.y };
+    unsafe {
+        to_mat4(ll::ovrMatrix4f_OrthoSubProjection(from_mat4(projection),
+                                                   scale,
+                                                   ortho_distance,
+                                                   eye_view_adjust_x))
+    }
+}
+
 #[derive(Debug, Copy, Clone)]
 pub enum HmdType {
     None,
@@ -350,15 +520,136 @@ impl HmdType {
     }
 }
 
-pub struct Ovr;
+#[derive(Debug, Clone)]
+pub enum Error {
+    InitFailed(Option<String>),
+    AlreadyInitialized,
+    NoHmd(isize),
+    DebugHmdFailed(HmdType, Option<String>),
+    SensorStartFailed {
+        missing: SensorCapabilities,
+        message: Option<String>
+    },
+    NoSensorDescription(Option<String>),
+    RenderingConfigFailed(Option<String>),
+    DistortionMeshFailed(Option<String>),
+    // a property name contains a nul byte
+    InvalidPropertyName(String),
+    // a string value passed for a property contains a nul byte
+    InvalidPropertyValue(String),
+    // the property does not exist or is read only
+    PropertyNotSet(String),
+    Sdk(String)
+}
+
+impl Error {
+    // the message the SDK recorded for the failure, if there was one
+    pub fn sdk_message(&self) -> Option<&str> {
+        match *self {
+            Error::InitFailed(ref msg) |
+            Error::DebugHmdFailed(_, ref msg) |
+            Error::SensorStartFailed { message: ref msg, .. } |
+            Error::NoSensorDescription(ref msg) |
+            Error::RenderingConfigFailed(ref msg) |
+            Error::DistortionMeshFailed(ref msg) => msg.as_ref().map(|m| &m[..]),
+            Error::Sdk(ref msg) => Some(&msg[..]),
+            Error::AlreadyInitialized |
+            Error::NoHmd(_) |
+            Error::InvalidPropertyName(_) |
+            Error::InvalidPropertyValue(_) |
+            Error::PropertyNotSet(_) => None
+        }
+    }
+}
+
+impl error::Error for Error {
+    fn description(&self) -> &str {
+        match *self {
+            Error::InitFailed(_) => "could not initialize the Oculus SDK",
+            Error::AlreadyInitialized => "the Oculus SDK is already initialized",
+            Error::NoHmd(_) => "no hmd at index",
+            Error::DebugHmdFailed(_, _) => "could not create debug hmd",
+            Error::SensorStartFailed { .. } => "sensor could not be started",
+            Error::NoSensorDescription(_) => "sensor description unavailable",
+            Error::RenderingConfigFailed(_) => "rendering configuration was rejected",
+            Error::DistortionMeshFailed(_) => "could not create distortion mesh",
+            Error::InvalidPropertyName(_) => "property names can not contain nul bytes",
+            Error::InvalidPropertyValue(_) => "property values can not contain nul bytes",
+            Error::PropertyNotSet(_) => "property does not exist or is read only",
+            Error::Sdk(_) => "Oculus SDK error"
+        }
+    }
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        use std::error::Error as StdError;
+
+        match *self {
+            Error::NoHmd(index) => return write!(f, "no hmd at index {}", index),
+            Error::Sdk(ref msg) => return write!(f, "Oculus SDK error: {}", msg),
+            Error::InvalidPropertyName(ref name) =>
+                return write!(f, "property name {:?} contains a nul byte", name),
+            Error::InvalidPropertyValue(ref value) =>
+                return write!(f, "property value {:?} contains a nul byte", value),
+            Error::PropertyNotSet(ref name) =>
+                return write!(f, "property {} does not exist or is read only", name),
+            Error::DebugHmdFailed(hmd_type, _) =>
+                try!(write!(f, "could not create debug hmd of type {:?}", hmd_type)),
+            Error::SensorStartFailed { missing, .. } => {
+                try!(write!(f, "{}", self.description()));
+                if missing.flags != 0 {
+                    try!(write!(f, ", missing required capabilities: {}",
+                                missing.names().connect(", ")));
+                }
+            }
+            _ => try!(write!(f, "{}", self.description()))
+        }
+
+        match self.sdk_message() {
+            Some(msg) => write!(f, " ({})", msg),
+            None => Ok(())
+        }
+    }
+}
+
+// reads the last error recorded by the SDK, a null hmd reads the global error
+#[cfg(feature = "sdk")]
+fn last_error(ptr: *mut ll::Hmd) -> Option<String> {
+    unsafe {
+        let msg = ll::ovrHmd_GetLastError(ptr);
+        if msg.is_null() {
+            None
+        } else {
+            Some(from_buf(msg as *const u8))
+        }
+    }
+}
+
+// the SDK has a single global state, only one Ovr may own it at a time
+#[cfg(feature = "sdk")]
+static INITIALIZED: AtomicBool = ATOMIC_BOOL_INIT;
+
+// Handle to the initialized SDK, every Hmd borrows from it so the SDK
+// can not be shutdown while a device is still open
+#[cfg(feature = "sdk")]
+pub struct Ovr {
+    _private: ()
+}
 
+#[cfg(feature = "sdk")]
 impl Ovr {
-    pub fn init() -> Option<Ovr> {
+    pub fn init() -> Result<Ovr, Error> {
+        if INITIALIZED.compare_and_swap(false, true, Ordering::SeqCst) {
+            return Err(Error::AlreadyInitialized);
+        }
+
         unsafe {
             if ll::ovr_Initialize() {
-                Some(Ovr)
+                Ok(Ovr { _private: () })
             } else {
-                None
+                INITIALIZED.store(false, Ordering::SeqCst);
+                Err(Error::InitFailed(last_error(ptr::null_mut())))
             }
         }
     }
@@ -368,65 +659,69 @@ impl Ovr {
         unsafe { ll::ovrHmd_Detect() as isize }
     }
 
-    pub fn create_hmd(&self, index: isize) -> Option<Hmd> {
+    pub fn create_hmd<'a>(&'a self, index: isize) -> Result<Hmd<'a>, Error> {
         unsafe {
             let ptr = ll::ovrHmd_Create(index as i32);
             if !ptr.is_null() {
-                Some(Hmd { ptr: ptr })
+                Ok(Hmd { ptr: ptr, ovr: PhantomData })
             } else {
-                None
+                Err(Error::NoHmd(index))
             }
         }
     }
 
-    pub fn first_hmd(&self) -> Option<Hmd> {
+    pub fn first_hmd<'a>(&'a self) -> Result<Hmd<'a>, Error> {
         if self.detect() >= 1 {
             self.create_hmd(0)
         } else {
-            None
+            Err(Error::NoHmd(0))
         }
     }
 
-    pub fn create_hmd_debug(&self, hmd_type: HmdType) -> Option<Hmd> {
+    pub fn create_hmd_debug<'a>(&'a self, hmd_type: HmdType) -> Result<Hmd<'a>, Error> {
         unsafe {
             let ptr = ll::ovrHmd_CreateDebug(hmd_type.to_ll());
             if !ptr.is_null() {
-                Some(Hmd{ptr:ptr})
+                Ok(Hmd { ptr: ptr, ovr: PhantomData })
             } else {
-                None
+                Err(Error::DebugHmdFailed(hmd_type, last_error(ptr::null_mut())))
             }
         }
     }
 }
 
+#[cfg(feature = "sdk")]
 impl Drop for Ovr {
     fn drop(&mut self) {
         unsafe { ll::ovr_Shutdown(); }
+        INITIALIZED.store(false, Ordering::SeqCst);
     }
 }
 
-pub struct Hmd {
-    ptr: *mut ll::Hmd
+#[cfg(feature = "sdk")]
+pub struct Hmd<'a> {
+    ptr: *mut ll::Hmd,
+    ovr: PhantomData<&'a Ovr>
 }
 
-unsafe impl Sync for Hmd {}
-unsafe impl Send for Hmd {}
+#[cfg(feature = "sdk")]
+unsafe impl<'a> Sync for Hmd<'a> {}
+#[cfg(feature = "sdk")]
+unsafe impl<'a> Send for Hmd<'a> {}
 
-impl Drop for Hmd {
+#[cfg(feature = "sdk")]
+impl<'a> Drop for Hmd<'a> {
     fn drop(&mut self) {
         unsafe {ll::ovrHmd_Destroy(self.ptr)}
     }
 }
 
-impl Hmd {
-    pub fn get_last_error(&self) -> Result<(), String> {
-        unsafe {
-            let ptr = ll::ovrHmd_GetLastError(self.ptr);
-            if ptr.is_null() {
-                Ok(())
-            } else {
-                Err(from_buf(ptr as *const u8))
-            }
+#[cfg(feature = "sdk")]
+impl<'a> Hmd<'a> {
+    pub fn get_last_error(&self) -> Result<(), Error> {
+        match last_error(self.ptr) {
+            None => Ok(()),
+            Some(msg) => Err(Error::Sdk(msg))
         }
     }
 
@@ -444,33 +739,33 @@ impl Hmd {
         }
     }
 
-    pub fn start_sensor(&self,
+    // starts tracking, the sensor state is read from the returned
+    // TrackingHmd. On failure the hmd is handed back so it can be started
+    // again with fewer required capabilities.
+    pub fn start_sensor(self,
                         supported: SensorCapabilities,
-                        required: SensorCapabilities) -> bool {
-        unsafe {
+                        required: SensorCapabilities) -> Result<TrackingHmd<'a>, StateError<Hmd<'a>>> {
+        let started = unsafe {
             ll::ovrHmd_StartSensor(self.ptr, supported.flags, required.flags)
-        }
-    }
-
-    pub fn stop_sensor(&self) {
-        unsafe {
-            ll::ovrHmd_StopSensor(self.ptr)
-        }
-    }
-
-    pub fn reset_sensor(&self) {
-        unsafe {
-            ll::ovrHmd_StopSensor(self.ptr)
-        }
-    }
+        };
 
-    pub fn get_sensor_state(&self, abs_time: f64) -> SensorState {
-        unsafe {
-            SensorState::from_ll(ll::ovrHmd_GetSensorState(self.ptr, abs_time))
+        if started {
+            Ok(TrackingHmd { hmd: self })
+        } else {
+            // get_description calls into the SDK too, read the error first
+            let message = last_error(self.ptr);
+            let available = self.get_description().sensor_capabilities;
+            let error = Error::SensorStartFailed {
+                missing: SensorCapabilities {
+                    flags: required.flags & !available.flags
+                },
+                message: message
+            };
+            Err(StateError { hmd: self, error: error })
         }
     }
 
-    pub fn get_sensor_description(&self) -> Option<SensorDescription> {
+    pub fn get_sensor_description(&self) -> Result<SensorDescription, Error> {
         unsafe {
             let mut c_desc = ll::SensorDesc {
                 vendor_id: 0,
@@ -479,9 +774,9 @@ impl Hmd {
             };
 
             if !ll::ovrHmd_GetSensorDesc(self.ptr, &mut c_desc as *mut ll::SensorDesc) {
-                None
+                Err(Error::NoSensorDescription(last_error(self.ptr)))
             } else {
-                Some(SensorDescription::from_ll(c_desc))
+                Ok(SensorDescription::from_ll(c_desc))
             }
         }
     }
@@ -506,10 +801,10 @@ impl Hmd {
         }
     }
 
-    pub fn configure_rendering<RC: ToRenderConfig>(&self,
-                               api_config: &RC,
-                               cap: DistortionCapabilities,
-                               eye_fov: PerEye<FovPort>) -> Option<PerEye<EyeRenderDescriptor>> {
+    fn configure<RC: ToRenderConfig>(&self,
+                                     api_config: &RC,
+                                     cap: DistortionCapabilities,
+                                     eye_fov: PerEye<FovPort>) -> Result<PerEye<EyeRenderDescriptor>, Error> {
         unsafe {
             let mut out: PerEye<ll::EyeRenderDesc> = PerEye::new(Default::default(),
                                                                  Default::default());
@@ -522,14 +817,209 @@ impl Hmd {
             );
 
             if was_started {
-                Some(out.map(|_, d| EyeRenderDescriptor::from_ll(d)))
+                Ok(out.map(|_, d| EyeRenderDescriptor::from_ll(d)))
+            } else {
+                Err(Error::RenderingConfigFailed(last_error(self.ptr)))
+            }
+        }
+    }
+
+    pub fn create_distortion_mesh(&self,
+                                  eye: Eye,
+                                  fov: FovPort,
+                                  cap: DistortionCapabilities) -> Result<DistortionMesh<'a>, Error> {
+        unsafe {
+            let mut mesh: ll::DistortionMesh = Default::default();
+            let created = ll::ovrHmd_CreateDistortionMesh(self.ptr,
+                                                          eye.to_ll(),
+                                                          fov.to_ll(),
+                                                          cap.flags,
+                                                          &mut mesh);
+            if created && !mesh.vertex_data.is_null() && !mesh.index_data.is_null() {
+                Ok(DistortionMesh { mesh: mesh, ovr: PhantomData })
             } else {
-                None
+                // the SDK may have allocated one of the buffers before failing
+                ll::ovrHmd_DestroyDistortionMesh(&mut mesh);
+                Err(Error::DistortionMeshFailed(last_error(self.ptr)))
             }
         }
     }
 
-    pub fn begin_frame(&self, frame_index: usize) -> FrameTiming {
+    pub fn get_render_description(&self, eye: Eye, fov: FovPort) -> EyeRenderDescriptor {
+        unsafe {
+            EyeRenderDescriptor::from_ll(
+                &ll::ovrHmd_GetRenderDesc(self.ptr, eye.to_ll(), fov.to_ll())
+            )
+        }
+    }
+
+    // timing for a frame that has not started yet, safe to call from
+    // a different thread than the one rendering
+    pub fn get_frame_timing(&self, frame_index: usize) -> FrameTiming {
+        unsafe {
+            FrameTiming::from_ll(
+                ll::ovrHmd_GetFrameTiming(self.ptr, frame_index as c_uint)
+            )
+        }
+    }
+
+    pub fn begin_frame_timing(&self, frame_index: usize) -> FrameTiming {
+        unsafe {
+            FrameTiming::from_ll(
+                ll::ovrHmd_BeginFrameTiming(self.ptr, frame_index as c_uint)
+            )
+        }
+    }
+
+    // must be called after the buffers have been swapped and the gpu synced
+    pub fn end_frame_timing(&self) {
+        unsafe {
+            ll::ovrHmd_EndFrameTiming(self.ptr);
+        }
+    }
+
+    pub fn reset_frame_timing(&self, frame_index: usize) {
+        unsafe {
+            ll::ovrHmd_ResetFrameTiming(self.ptr, frame_index as c_uint);
+        }
+    }
+
+    // returns the matrices the distortion shader blends between using
+    // the vertex timewarp factor
+    pub fn get_eye_timewarp_matrices(&self, eye: Eye, pose: Pose) -> [Matrix4<f32>; 2] {
+        unsafe {
+            let mut out: [ll::Matrix4f; 2] = [Default::default(); 2];
+            ll::ovrHmd_GetEyeTimewarpMatrices(self.ptr,
+                                              eye.to_ll(),
+                                              pose.to_ll(),
+                                              &mut out[0]);
+            [to_mat4(out[0]), to_mat4(out[1])]
+        }
+    }
+}
+
+// A failed change of state, with the hmd back in the state it was in
+pub struct StateError<H> {
+    pub hmd: H,
+    pub error: Error
+}
+
+impl<H> fmt::Debug for StateError<H> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "StateError {{ error: {:?} }}", self.error)
+    }
+}
+
+impl<H> fmt::Display for StateError<H> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.error)
+    }
+}
+
+impl<H> From<StateError<H>> for Error {
+    fn from(err: StateError<H>) -> Error {
+        err.error
+    }
+}
+
+// An hmd with a started sensor, returned by Hmd::start_sensor. The rest of
+// the Hmd is reached through Deref.
+#[cfg(feature = "sdk")]
+pub struct TrackingHmd<'a> {
+    hmd: Hmd<'a>
+}
+
+#[cfg(feature = "sdk")]
+impl<'a> Deref for TrackingHmd<'a> {
+    type Target = Hmd<'a>;
+
+    fn deref(&self) -> &Hmd<'a> {
+        &self.hmd
+    }
+}
+
+#[cfg(feature = "sdk")]
+impl<'a> TrackingHmd<'a> {
+    pub fn get_sensor_state(&self, abs_time: f64) -> SensorState {
+        unsafe {
+            SensorState::from_ll(ll::ovrHmd_GetSensorState(self.ptr, abs_time))
+        }
+    }
+
+    pub fn reset_sensor(&self) {
+        unsafe {
+            ll::ovrHmd_ResetSensor(self.ptr)
+        }
+    }
+
+    pub fn get_eye_pose(&self, eye: Eye) -> Pose {
+        unsafe {
+            Pose::from_ll(ll::ovrHmd_GetEyePose(self.ptr, eye.to_ll()))
+        }
+    }
+
+    pub fn stop_sensor(self) -> Hmd<'a> {
+        unsafe {
+            ll::ovrHmd_StopSensor(self.ptr)
+        }
+        self.hmd
+    }
+
+    // configures the SDK's distortion rendering, frames are rendered with
+    // the returned RenderingHmd. On failure the tracking hmd is handed back.
+    pub fn configure_rendering<RC: ToRenderConfig>(self,
+                               api_config: &RC,
+                               cap: DistortionCapabilities,
+                               eye_fov: PerEye<FovPort>) -> Result<RenderingHmd<'a>, StateError<TrackingHmd<'a>>> {
+        let configured = self.configure(api_config, cap, eye_fov);
+        match configured {
+            Ok(desc) => Ok(RenderingHmd { hmd: self, eye_render_descriptors: desc }),
+            Err(err) => Err(StateError { hmd: self, error: err })
+        }
+    }
+}
+
+// An hmd with a started sensor and the SDK's distortion rendering
+// configured, returned by TrackingHmd::configure_rendering. Frames can only
+// be rendered from here, the rest is reached through Deref.
+#[cfg(feature = "sdk")]
+pub struct RenderingHmd<'a> {
+    hmd: TrackingHmd<'a>,
+    eye_render_descriptors: PerEye<EyeRenderDescriptor>
+}
+
+#[cfg(feature = "sdk")]
+impl<'a> Deref for RenderingHmd<'a> {
+    type Target = TrackingHmd<'a>;
+
+    fn deref(&self) -> &TrackingHmd<'a> {
+        &self.hmd
+    }
+}
+
+#[cfg(feature = "sdk")]
+impl<'a> RenderingHmd<'a> {
+    // the descriptors of the current configuration
+    pub fn eye_render_descriptors(&self) -> &PerEye<EyeRenderDescriptor> {
+        &self.eye_render_descriptors
+    }
+
+    // configures rendering again in place, for engines that have to change
+    // it while running, after a window resize for example. On failure the
+    // old descriptors are kept but the SDK may not render anymore, frames
+    // should not be rendered until a reconfigure succeeds.
+    pub fn reconfigure<RC: ToRenderConfig>(&mut self,
+                                           api_config: &RC,
+                                           cap: DistortionCapabilities,
+                                           eye_fov: PerEye<FovPort>) -> Result<PerEye<EyeRenderDescriptor>, Error> {
+        let desc = try!(self.configure(api_config, cap, eye_fov));
+        self.eye_render_descriptors = desc;
+        Ok(desc)
+    }
+
+    // the SDK's begin and end calls, only used by the Frame guards which
+    // keep them paired
+    fn begin_frame(&self, frame_index: usize) -> FrameTiming {
         unsafe {
             FrameTiming::from_ll(
                 ll::ovrHmd_BeginFrame(self.ptr, frame_index as c_uint)
@@ -537,22 +1027,22 @@ impl Hmd {
         }
     }
 
-    pub fn end_frame(&self) {
+    fn end_frame(&self) {
         unsafe {
             ll::ovrHmd_EndFrame(self.ptr);
         }
     }
 
-    pub fn begin_eye_render(&self, eye: Eye) -> Pose {
+    fn begin_eye_render(&self, eye: Eye) -> Pose {
         unsafe {
             Pose::from_ll(ll::ovrHmd_BeginEyeRender(self.ptr, eye.to_ll()))
         }
     }
 
-    pub fn end_eye_render<T: ToTexture>(&self,
-                                        eye: Eye,
-                                        pose: Pose,
-                                        texture: &T) {
+    fn end_eye_render<T: ToTexture>(&self,
+                                    eye: Eye,
+                                    pose: Pose,
+                                    texture: &T) {
         unsafe {
             ll::ovrHmd_EndEyeRender(self.ptr,
                                     eye.to_ll(),
@@ -562,6 +1052,42 @@ impl Hmd {
     }
 }
 
+// Vertex and index buffers generated by the SDK for client side distortion,
+// the buffers are released when the mesh is dropped. They are owned by the
+// SDK allocator so the mesh may outlive its Hmd but not the Ovr.
+#[cfg(feature = "sdk")]
+pub struct DistortionMesh<'a> {
+    mesh: ll::DistortionMesh,
+    ovr: PhantomData<&'a Ovr>
+}
+
+#[cfg(feature = "sdk")]
+unsafe impl<'a> Send for DistortionMesh<'a> {}
+
+#[cfg(feature = "sdk")]
+impl<'a> DistortionMesh<'a> {
+    pub fn vertices<'b>(&'b self) -> &'b [ll::DistortionVertex] {
+        unsafe {
+            slice::from_raw_parts(self.mesh.vertex_data as *const ll::DistortionVertex,
+                                  self.mesh.vertex_count as usize)
+        }
+    }
+
+    pub fn indices<'b>(&'b self) -> &'b [u16] {
+        unsafe {
+            slice::from_raw_parts(self.mesh.index_data as *const u16,
+                                  self.mesh.index_count as usize)
+        }
+    }
+}
+
+#[cfg(feature = "sdk")]
+impl<'a> Drop for DistortionMesh<'a> {
+    fn drop(&mut self) {
+        unsafe { ll::ovrHmd_DestroyDistortionMesh(&mut self.mesh) }
+    }
+}
+
 #[derive(Debug, Copy, Clone)]
 pub struct HmdCapabilities {
     flags: c_uint
@@ -695,6 +1221,14 @@ impl SensorCapabilities {
             }
         }
     }
+
+    pub fn names(&self) -> Vec<&'static str> {
+        let mut names = Vec::new();
+        if self.orientation() { names.push("orientation"); }
+        if self.yaw_correction() { names.push("yaw_correction"); }
+        if self.position() { names.push("position"); }
+        names
+    }
 }
 
 #[derive(Debug, Copy, Clone)]
@@ -780,6 +1314,15 @@ impl Status {
         self.flags & ll::Status_HmdConnected ==
             ll::Status_HmdConnected
     }
+
+    pub fn names(&self) -> Vec<&'static str> {
+        let mut names = Vec::new();
+        if self.orientation_tracked() { names.push("orientation_tracked"); }
+        if self.position_tracked() { names.push("position_tracked"); }
+        if self.position_connected() { names.push("position_connected"); }
+        if self.hmd_connected() { names.push("hmd_connected"); }
+        names
+    }
 }
 
 fn to_quat(q: ll::Quaternionf) -> Quaternion<f32> {
@@ -799,6 +1342,15 @@ fn to_mat4(ll: ll::Matrix4f) -> Matrix4<f32> {
     )
 }
 
+fn from_mat4(m: &Matrix4<f32>) -> ll::Matrix4f {
+    ll::Matrix4f {
+        m11: m.x.x, m12: m.y.x, m13: m.z.x, m14: m.w.x,
+        m21: m.x.y, m22: m.y.y, m23: m.z.y, m24: m.w.y,
+        m31: m.x.z, m32: m.y.z, m33: m.z.z, m34: m.w.z,
+        m41: m.x.w, m42: m.y.w, m43: m.z.w, m44: m.w.w
+    }
+}
+
 fn from_quat(q: Quaternion<f32>) -> ll::Quaternionf {
     ll::Quaternionf {
         x: q.v.x, y: q.v.y, z: q.v.z, w: q.s
@@ -831,6 +1383,52 @@ impl Pose {
             position: from_vec3(self.position),
         }
     }
+
+    // the orientation as (yaw, pitch, roll) in radians, yaw is around the
+    // y axis, pitch around x and roll around z, applied in that order
+    pub fn yaw_pitch_roll(&self) -> (f32, f32, f32) {
+        math::quat_to_euler(self.orientation)
+    }
+
+    pub fn identity() -> Pose {
+        Pose {
+            orientation: math::quat_identity(),
+            position: Vector3::new(0., 0., 0.)
+        }
+    }
+
+    // the transform from the pose's frame to the frame it is given in,
+    // the rotation is applied first and then the translation
+    pub fn to_matrix(&self) -> Matrix4<f32> {
+        math::rigid_to_mat4(self.orientation, self.position)
+    }
+
+    pub fn inverse(&self) -> Pose {
+        let orientation = math::quat_conjugate(self.orientation);
+        Pose {
+            orientation: orientation,
+            position: math::vec3_scale(math::quat_rotate(orientation, self.position), -1.)
+        }
+    }
+
+    // self * other, the pose that applies other first and then self. With
+    // a_from_b.compose(&b_from_c) the result is a_from_c.
+    pub fn compose(&self, other: &Pose) -> Pose {
+        Pose {
+            orientation: math::quat_normalize(math::quat_mul(self.orientation, other.orientation)),
+            position: math::vec3_add(self.position, math::quat_rotate(self.orientation, other.position))
+        }
+    }
+
+    // the same transform with the z axis flipped, converts between the
+    // SDK's right handed coordinates and left handed ones
+    pub fn mirror_z(&self) -> Pose {
+        let q = self.orientation;
+        Pose {
+            orientation: Quaternion::new(q.s, -q.v.x, -q.v.y, q.v.z),
+            position: Vector3::new(self.position.x, self.position.y, -self.position.z)
+        }
+    }
 }
 
 #[derive(Debug, Copy, Clone)]
@@ -854,6 +1452,35 @@ impl PoseState {
             time_in_seconds: pose.time_in_seconds as f64
         }
     }
+
+    // interpolates every field, slerp for the orientation and linear
+    // for everything else, amount 0 is self and 1 is other
+    pub fn lerp(&self, other: &PoseState, amount: f32) -> PoseState {
+        PoseState {
+            pose: Pose {
+                orientation: math::quat_slerp(self.pose.orientation, other.pose.orientation, amount),
+                position: math::vec3_lerp(self.pose.position, other.pose.position, amount)
+            },
+            angular_velocity: math::vec3_lerp(self.angular_velocity, other.angular_velocity, amount),
+            linear_velocity: math::vec3_lerp(self.linear_velocity, other.linear_velocity, amount),
+            angular_acceleration: math::vec3_lerp(self.angular_acceleration,
+                                                  other.angular_acceleration, amount),
+            linear_acceleration: math::vec3_lerp(self.linear_acceleration,
+                                                 other.linear_acceleration, amount),
+            time_in_seconds: self.time_in_seconds +
+                (other.time_in_seconds - self.time_in_seconds) * amount as f64
+        }
+    }
+
+    // the state dt seconds later assuming constant velocity, which is
+    // what the SDK's own prediction does
+    pub fn predict(&self, dt: f64) -> PoseState {
+        Predictor::ConstantVelocity.predict(self, dt)
+    }
+
+    pub fn predict_with(&self, predictor: Predictor, dt: f64) -> PoseState {
+        predictor.predict(self, dt)
+    }
 }
 
 #[derive(Copy, Clone)]
@@ -873,6 +1500,53 @@ impl SensorState {
             status_flags: Status{flags: ss.status_flags}
         }
     }
+
+    // predicts the recorded state forward to abs_time, an alternative to
+    // the predicted state the source filled in
+    pub fn predict_at(&self, predictor: Predictor, abs_time: f64) -> PoseState {
+        self.recorded.predict_with(predictor, abs_time - self.recorded.time_in_seconds)
+    }
+}
+
+// anything that can be asked for the sensor state at an absolute time,
+// a real Hmd, a SimulatedHmd or a recording being replayed
+pub trait SensorSource {
+    fn get_sensor_state(&self, abs_time: f64) -> SensorState;
+}
+
+#[cfg(feature = "sdk")]
+impl<'a> layout::FovTextureSize for Hmd<'a> {
+    fn get_fov_texture_size(&self, eye: Eye, fov: FovPort, pixels_per_display_pixel: f32) -> ll::Sizei {
+        Hmd::get_fov_texture_size(self, eye, fov, pixels_per_display_pixel)
+    }
+}
+
+#[cfg(feature = "sdk")]
+impl<'a> layout::FovTextureSize for TrackingHmd<'a> {
+    fn get_fov_texture_size(&self, eye: Eye, fov: FovPort, pixels_per_display_pixel: f32) -> ll::Sizei {
+        Hmd::get_fov_texture_size(self, eye, fov, pixels_per_display_pixel)
+    }
+}
+
+#[cfg(feature = "sdk")]
+impl<'a> layout::FovTextureSize for RenderingHmd<'a> {
+    fn get_fov_texture_size(&self, eye: Eye, fov: FovPort, pixels_per_display_pixel: f32) -> ll::Sizei {
+        Hmd::get_fov_texture_size(self, eye, fov, pixels_per_display_pixel)
+    }
+}
+
+#[cfg(feature = "sdk")]
+impl<'a> SensorSource for TrackingHmd<'a> {
+    fn get_sensor_state(&self, abs_time: f64) -> SensorState {
+        TrackingHmd::get_sensor_state(self, abs_time)
+    }
+}
+
+#[cfg(feature = "sdk")]
+impl<'a> SensorSource for RenderingHmd<'a> {
+    fn get_sensor_state(&self, abs_time: f64) -> SensorState {
+        TrackingHmd::get_sensor_state(self, abs_time)
+    }
 }
 #[derive(Debug)]
 pub struct SensorDescription {
@@ -912,6 +1586,13 @@ impl Eye {
             Eye::Right => ll::Eye_Right
         }
     }
+
+    fn from_common(eye: vr_common::Eye) -> Eye {
+        match eye {
+            vr_common::Eye::Left => Eye::Left,
+            vr_common::Eye::Right => Eye::Right
+        }
+    }
 }
 
 #[derive(Debug, Copy, Clone)]
@@ -957,6 +1638,13 @@ pub struct HmdDescriptionEye {
     pub max_eye_fov: FovPort,
 }
 
+impl HmdDescriptionEye {
+    // the part of fov the eye can actually see
+    pub fn clamp_fov(&self, fov: &FovPort) -> FovPort {
+        fov.intersection(&self.max_eye_fov)
+    }
+}
+
 #[derive(Debug, Clone)]
 pub struct HmdDescription {
     pub hmd_type: HmdType,
@@ -1041,6 +1729,40 @@ impl EyeRenderDescriptor {
                                       d.view_adjust.z)
         }
     }
+
+    // the size of one pixel at the center of the eye's fov in tan angle
+    // units, the ortho_scale of ortho_sub_projection for pixel sized units
+    pub fn ortho_scale(&self) -> Vector2<f32> {
+        Vector2::new(1. / self.pixels_per_tan_angle_at_center.x,
+                     1. / self.pixels_per_tan_angle_at_center.y)
+    }
+
+    // the projection for a 2D layer distance meters in front of this eye in
+    // pixel units, projection is the eye's own projection matrix
+    #[cfg(feature = "sdk")]
+    pub fn ortho_projection(&self, projection: &Matrix4<f32>, distance: f32) -> Matrix4<f32> {
+        ortho_sub_projection(projection, self.ortho_scale(), distance, self.view_adjust.x)
+    }
+
+    // the eye's view matrix for a head pose from the sensor. world_from_tracking
+    // places the tracking origin in the world, use Pose::identity() when they
+    // are the same. Poses are in the SDK's right handed coordinates, with
+    // right_handed false the matrix is converted for a left handed world.
+    pub fn view_matrix(&self, head_pose: &Pose, world_from_tracking: &Pose,
+                       right_handed: bool) -> Matrix4<f32> {
+        let head_from_world = world_from_tracking.compose(head_pose).inverse();
+        let eye_from_head = Pose {
+            orientation: math::quat_identity(),
+            position: self.view_adjust
+        };
+        let eye_from_world = eye_from_head.compose(&head_from_world);
+
+        if right_handed {
+            eye_from_world.to_matrix()
+        } else {
+            eye_from_world.mirror_z().to_matrix()
+        }
+    }
 }
 
 #[derive(Debug, Copy, Clone)]
@@ -1157,7 +1879,100 @@ pub struct FovPort {
     pub right: f32
 }
 
+fn tan_degrees(degrees: f32) -> f32 {
+    degrees.to_radians().tan()
+}
+
+fn degrees_tan(tan: f32) -> f32 {
+    tan.atan().to_degrees()
+}
+
 impl FovPort {
+    // from the angle of each side to the center in degrees
+    pub fn from_degrees(up: f32, down: f32, left: f32, right: f32) -> FovPort {
+        FovPort {
+            up: tan_degrees(up),
+            down: tan_degrees(down),
+            left: tan_degrees(left),
+            right: tan_degrees(right)
+        }
+    }
+
+    // a symmetric fov from the full horizontal and vertical angles in degrees
+    pub fn from_field_of_view(horizontal: f32, vertical: f32) -> FovPort {
+        FovPort::from_degrees(vertical * 0.5, vertical * 0.5, horizontal * 0.5, horizontal * 0.5)
+    }
+
+    // the angle of each side to the center in degrees, as (up, down, left, right)
+    pub fn to_degrees(&self) -> (f32, f32, f32, f32) {
+        (degrees_tan(self.up), degrees_tan(self.down),
+         degrees_tan(self.left), degrees_tan(self.right))
+    }
+
+    // the full horizontal angle in degrees
+    pub fn horizontal_degrees(&self) -> f32 {
+        degrees_tan(self.left) + degrees_tan(self.right)
+    }
+
+    // the full vertical angle in degrees
+    pub fn vertical_degrees(&self) -> f32 {
+        degrees_tan(self.up) + degrees_tan(self.down)
+    }
+
+    // the smallest fov that covers both
+    pub fn union(&self, other: &FovPort) -> FovPort {
+        FovPort {
+            up: self.up.max(other.up),
+            down: self.down.max(other.down),
+            left: self.left.max(other.left),
+            right: self.right.max(other.right)
+        }
+    }
+
+    // the part both cover, sides may end up negative when they do not overlap
+    pub fn intersection(&self, other: &FovPort) -> FovPort {
+        FovPort {
+            up: self.up.min(other.up),
+            down: self.down.min(other.down),
+            left: self.left.min(other.left),
+            right: self.right.min(other.right)
+        }
+    }
+
+    // scales the tangents, so a factor of 0.8 gives 80% of the image
+    // width and height rather than of the angles
+    pub fn scale(&self, factor: f32) -> FovPort {
+        FovPort {
+            up: self.up * factor,
+            down: self.down * factor,
+            left: self.left * factor,
+            right: self.right * factor
+        }
+    }
+
+    // the smallest fov centered on the view direction that covers this one,
+    // for shadow maps or culling that assume a symmetric frustum
+    pub fn symmetric(&self) -> FovPort {
+        let vertical = self.up.max(self.down);
+        let horizontal = self.left.max(self.right);
+        FovPort {
+            up: vertical,
+            down: vertical,
+            left: horizontal,
+            right: horizontal
+        }
+    }
+
+    // a single frustum containing both eyes' frusta, for culling once for
+    // both eyes. The eyes are ipd meters apart, so the frustum's apex sits
+    // behind the point between them, the returned distance is how far.
+    pub fn combined(fovs: &PerEye<FovPort>, ipd: f32) -> (FovPort, f32) {
+        let fov = fovs.left.union(&fovs.right);
+        let narrowest = fov.left.min(fov.right);
+        let back = if narrowest > 0. { ipd * 0.5 / narrowest } else { 0. };
+        (fov, back)
+    }
+
     fn from_ll(ll: ll::FovPort) -> FovPort {
         FovPort {
             up: ll.up_tan as f32,
@@ -1176,10 +1991,319 @@ impl FovPort {
         }
     }
 
+    fn to_common(&self) -> vr_common::Fov {
+        vr_common::Fov {
+            up: self.up,
+            down: self.down,
+            left: self.left,
+            right: self.right
+        }
+    }
+
+    #[cfg(feature = "sdk")]
     pub fn projection(&self, znear: f32, zfar: f32, right_handed: bool) -> Matrix4<f32> {
         unsafe {
             let mat = ll::ovrMatrix4f_Projection(self.to_ll(), znear, zfar, right_handed);
             to_mat4(mat)
         }
     }
-}
\ No newline at end of file
+
+    // a projection built in rust, see Projection for the options the SDK
+    // does not have
+    pub fn projection_builder(&self, znear: f32, zfar: f32) -> Projection {
+        Projection::new(*self, znear, zfar)
+    }
+}
+
+fn to_viewport(r: ll::Recti) -> Viewport {
+    Viewport {
+        x: r.pos.x,
+        y: r.pos.y,
+        width: r.size.x as u32,
+        height: r.size.y as u32
+    }
+}
+
+#[cfg(feature = "sdk")]
+impl<'a> HeadMountedDisplay for TrackingHmd<'a> {
+    fn description(&self) -> DisplayDescription {
+        let desc = self.get_description();
+        DisplayDescription {
+            name: desc.product_name,
+            resolution: (desc.resolution.x as u32, desc.resolution.y as u32),
+            window_position: (desc.window_position.x, desc.window_position.y)
+        }
+    }
+
+    fn recommended_render_size(&self, eye: vr_common::Eye) -> (u32, u32) {
+        let eye = Eye::from_common(eye);
+        let fov = self.get_description().eye_fovs.eye(eye).default_eye_fov;
+        let size = self.get_fov_texture_size(eye, fov, 1.);
+        (size.x as u32, size.y as u32)
+    }
+
+    fn fov(&self, eye: vr_common::Eye) -> vr_common::Fov {
+        let eye = Eye::from_common(eye);
+        self.get_description().eye_fovs.eye(eye).default_eye_fov.to_common()
+    }
+
+    fn projection(&self, eye: vr_common::Eye, znear: f32, zfar: f32) -> Matrix4<f32> {
+        let eye = Eye::from_common(eye);
+        self.get_description().eye_fovs.eye(eye).default_eye_fov
+            .projection_builder(znear, zfar)
+            .depth_range(DepthRange::MinusOneToOne)
+            .build()
+    }
+
+    fn eye_viewport(&self, eye: vr_common::Eye) -> Viewport {
+        let eye = Eye::from_common(eye);
+        let fov = self.get_description().eye_fovs.eye(eye).default_eye_fov;
+        to_viewport(self.get_render_description(eye, fov).distorted_viewport)
+    }
+
+    fn head_pose(&self, seconds_from_now: f32) -> Option<Matrix4<f32>> {
+        let state = self.get_sensor_state(get_time() + seconds_from_now as f64);
+        if state.status_flags.orientation_tracked() {
+            let pose = state.predicted.pose;
+            Some(math::rigid_to_mat4(pose.orientation, pose.position))
+        } else {
+            None
+        }
+    }
+
+    fn recenter(&mut self) {
+        self.reset_sensor();
+    }
+}
+
+#[cfg(feature = "sdk")]
+impl<'a> HeadMountedDisplay for RenderingHmd<'a> {
+    fn description(&self) -> DisplayDescription {
+        self.hmd.description()
+    }
+
+    fn recommended_render_size(&self, eye: vr_common::Eye) -> (u32, u32) {
+        self.hmd.recommended_render_size(eye)
+    }
+
+    fn fov(&self, eye: vr_common::Eye) -> vr_common::Fov {
+        self.hmd.fov(eye)
+    }
+
+    fn projection(&self, eye: vr_common::Eye, znear: f32, zfar: f32) -> Matrix4<f32> {
+        self.hmd.projection(eye, znear, zfar)
+    }
+
+    fn eye_viewport(&self, eye: vr_common::Eye) -> Viewport {
+        self.hmd.eye_viewport(eye)
+    }
+
+    fn head_pose(&self, seconds_from_now: f32) -> Option<Matrix4<f32>> {
+        self.hmd.head_pose(seconds_from_now)
+    }
+
+    fn recenter(&mut self) {
+        self.hmd.recenter();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::default::Default;
+    use std::f32::consts::FRAC_PI_2;
+
+    use cgmath::{Matrix4, Quaternion, Vector2, Vector3};
+
+    use super::{Eye, EyeRenderDescriptor, FovPort, Pose};
+
+    const EPSILON: f32 = 1e-5;
+
+    // cgmath matrices as rows of plain arrays, so the expected values can
+    // be written out the way they are printed in the SDK's documentation
+    fn rows(m: &Matrix4<f32>) -> [[f32; 4]; 4] {
+        let c = [m.x, m.y, m.z, m.w];
+        let mut r = [[0.; 4]; 4];
+        for i in 0..4 {
+            r[0][i] = c[i].x;
+            r[1][i] = c[i].y;
+            r[2][i] = c[i].z;
+            r[3][i] = c[i].w;
+        }
+        r
+    }
+
+    fn mul(a: &[[f32; 4]; 4], b: &[[f32; 4]; 4]) -> [[f32; 4]; 4] {
+        let mut r = [[0.; 4]; 4];
+        for i in 0..4 {
+            for j in 0..4 {
+                for k in 0..4 {
+                    r[i][j] += a[i][k] * b[k][j];
+                }
+            }
+        }
+        r
+    }
+
+    // gauss jordan with partial pivoting, the test matrices are invertible
+    fn invert(m: &[[f32; 4]; 4]) -> [[f32; 4]; 4] {
+        let mut a = *m;
+        let mut r = [[0.; 4]; 4];
+        for i in 0..4 {
+            r[i][i] = 1.;
+        }
+        for col in 0..4 {
+            let mut pivot = col;
+            for row in col + 1..4 {
+                if a[row][col].abs() > a[pivot][col].abs() {
+                    pivot = row;
+                }
+            }
+            a.swap(col, pivot);
+            r.swap(col, pivot);
+            let p = a[col][col];
+            for j in 0..4 {
+                a[col][j] /= p;
+                r[col][j] /= p;
+            }
+            for row in 0..4 {
+                if row != col {
+                    let f = a[row][col];
+                    for j in 0..4 {
+                        a[row][j] -= f * a[col][j];
+                        r[row][j] -= f * r[col][j];
+                    }
+                }
+            }
+        }
+        r
+    }
+
+    fn translation(v: Vector3<f32>) -> [[f32; 4]; 4] {
+        [[1., 0., 0., v.x],
+         [0., 1., 0., v.y],
+         [0., 0., 1., v.z],
+         [0., 0., 0., 1.]]
+    }
+
+    // the rotation of a unit quaternion written out, independent of math.rs
+    fn pose_rows(pose: &Pose) -> [[f32; 4]; 4] {
+        let (w, x, y, z) = (pose.orientation.s, pose.orientation.v.x,
+                            pose.orientation.v.y, pose.orientation.v.z);
+        let p = pose.position;
+        [[1. - 2. * (y * y + z * z), 2. * (x * y - w * z), 2. * (x * z + w * y), p.x],
+         [2. * (x * y + w * z), 1. - 2. * (x * x + z * z), 2. * (y * z - w * x), p.y],
+         [2. * (x * z - w * y), 2. * (y * z + w * x), 1. - 2. * (x * x + y * y), p.z],
+         [0., 0., 0., 1.]]
+    }
+
+    fn assert_rows_eq(a: &[[f32; 4]; 4], b: &[[f32; 4]; 4]) {
+        for i in 0..4 {
+            for j in 0..4 {
+                assert!((a[i][j] - b[i][j]).abs() < EPSILON,
+                        "row {} column {}: {:?} != {:?}", i, j, a, b);
+            }
+        }
+    }
+
+    fn axis_angle(axis: Vector3<f32>, angle: f32) -> Quaternion<f32> {
+        let len = (axis.x * axis.x + axis.y * axis.y + axis.z * axis.z).sqrt();
+        let s = (angle * 0.5).sin() / len;
+        Quaternion::new((angle * 0.5).cos(), axis.x * s, axis.y * s, axis.z * s)
+    }
+
+    // a head turned and tilted away from the origin
+    fn head_pose() -> Pose {
+        Pose {
+            orientation: axis_angle(Vector3::new(0.3, 1., -0.2), 0.7),
+            position: Vector3::new(0.1, 1.6, -0.4)
+        }
+    }
+
+    fn eye_desc(eye: Eye, view_adjust_x: f32) -> EyeRenderDescriptor {
+        EyeRenderDescriptor {
+            eye: eye,
+            fov: FovPort { up: 1., down: 1., left: 1., right: 1. },
+            distorted_viewport: Default::default(),
+            pixels_per_tan_angle_at_center: Vector2::new(500., 500.),
+            view_adjust: Vector3::new(view_adjust_x, 0., 0.)
+        }
+    }
+
+    #[test]
+    fn pose_composed_with_its_inverse_is_identity() {
+        let pose = head_pose();
+        let identity = rows(&Pose::identity().to_matrix());
+        assert_rows_eq(&rows(&pose.compose(&pose.inverse()).to_matrix()), &identity);
+        assert_rows_eq(&rows(&pose.inverse().compose(&pose).to_matrix()), &identity);
+    }
+
+    #[test]
+    fn pose_matrix_is_rotation_then_translation() {
+        let pose = head_pose();
+        assert_rows_eq(&rows(&pose.to_matrix()), &pose_rows(&pose));
+        assert_rows_eq(&rows(&pose.inverse().to_matrix()), &invert(&pose_rows(&pose)));
+    }
+
+    #[test]
+    fn view_matrix_is_view_adjust_times_inverse_pose() {
+        let pose = head_pose();
+        let world_from_tracking = Pose {
+            orientation: axis_angle(Vector3::new(0., 1., 0.), 1.2),
+            position: Vector3::new(3., 0., 2.)
+        };
+
+        for &(eye, x) in [(Eye::Left, 0.032), (Eye::Right, -0.032)].iter() {
+            let desc = eye_desc(eye, x);
+            let world_from_head = mul(&pose_rows(&world_from_tracking), &pose_rows(&pose));
+            let expected = mul(&translation(desc.view_adjust), &invert(&world_from_head));
+
+            let view = desc.view_matrix(&pose, &world_from_tracking, true);
+            assert_rows_eq(&rows(&view), &expected);
+
+            // left handed is the same view seen through a z mirror
+            let mirror = [[1., 0., 0., 0.], [0., 1., 0., 0.], [0., 0., -1., 0.], [0., 0., 0., 1.]];
+            let left_handed = desc.view_matrix(&pose, &world_from_tracking, false);
+            assert_rows_eq(&rows(&left_handed), &mul(&mirror, &mul(&expected, &mirror)));
+        }
+    }
+
+    // printed by the SDK's own Matrix4f for head_pose in the world pose of
+    // view_matrix_is_view_adjust_times_inverse_pose, built the way
+    // OculusRoomTiny does: Translation(ViewAdjust) *
+    // Matrix4f(orientation.Inverted()) * Translation(-position)
+    #[test]
+    fn view_matrix_matches_the_sdks_matrix4f() {
+        let world_from_tracking = Pose {
+            orientation: axis_angle(Vector3::new(0., 1., 0.), 1.2),
+            position: Vector3::new(3., 0., 2.)
+        };
+        let sdk = [[[-0.2925473, -0.0587745, -0.9544432, 2.5868044],
+                    [0.1972029, 0.9729465, -0.1203588, -1.8698940],
+                    [0.9356961, -0.2234296, -0.2730423, -1.6536041],
+                    [0., 0., 0., 1.]],
+                   [[-0.2925473, -0.0587745, -0.9544432, 2.5228043],
+                    [0.1972029, 0.9729465, -0.1203588, -1.8698940],
+                    [0.9356961, -0.2234296, -0.2730423, -1.6536041],
+                    [0., 0., 0., 1.]]];
+
+        let eyes = [(Eye::Left, 0.032), (Eye::Right, -0.032)];
+        for (&(eye, x), expected) in eyes.iter().zip(sdk.iter()) {
+            let view = eye_desc(eye, x).view_matrix(&head_pose(), &world_from_tracking, true);
+            assert_rows_eq(&rows(&view), expected);
+        }
+    }
+
+    #[test]
+    fn mirror_z_matches_hand_computed_matrix() {
+        // turned 90 degrees to the left, x now points to -z
+        let pose = Pose {
+            orientation: axis_angle(Vector3::new(0., 1., 0.), FRAC_PI_2),
+            position: Vector3::new(1., 2., 3.)
+        };
+        let expected = [[0., 0., -1., 1.],
+                        [0., 1., 0., 2.],
+                        [1., 0., 0., -3.],
+                        [0., 0., 0., 1.]];
+        assert_rows_eq(&rows(&pose.mirror_z().to_matrix()), &expected);
+    }
+}
@@ -0,0 +1,235 @@
+// Small quaternion and vector helpers shared by the pure rust pose code.
+// Quaternions are expected to be unit length unless noted otherwise.
+
+use cgmath::{Matrix4, Quaternion, Vector3};
+
+pub fn vec3_add(a: Vector3<f32>, b: Vector3<f32>) -> Vector3<f32> {
+    Vector3::new(a.x + b.x, a.y + b.y, a.z + b.z)
+}
+
+pub fn vec3_sub(a: Vector3<f32>, b: Vector3<f32>) -> Vector3<f32> {
+    Vector3::new(a.x - b.x, a.y - b.y, a.z - b.z)
+}
+
+pub fn vec3_scale(a: Vector3<f32>, s: f32) -> Vector3<f32> {
+    Vector3::new(a.x * s, a.y * s, a.z * s)
+}
+
+pub fn vec3_dot(a: Vector3<f32>, b: Vector3<f32>) -> f32 {
+    a.x * b.x + a.y * b.y + a.z * b.z
+}
+
+pub fn vec3_length(a: Vector3<f32>) -> f32 {
+    vec3_dot(a, a).sqrt()
+}
+
+pub fn vec3_lerp(a: Vector3<f32>, b: Vector3<f32>, t: f32) -> Vector3<f32> {
+    vec3_add(a, vec3_scale(vec3_sub(b, a), t))
+}
+
+pub fn quat_identity() -> Quaternion<f32> {
+    Quaternion::new(1., 0., 0., 0.)
+}
+
+pub fn quat_mul(a: Quaternion<f32>, b: Quaternion<f32>) -> Quaternion<f32> {
+    Quaternion::new(
+        a.s * b.s - a.v.x * b.v.x - a.v.y * b.v.y - a.v.z * b.v.z,
+        a.s * b.v.x + a.v.x * b.s + a.v.y * b.v.z - a.v.z * b.v.y,
+        a.s * b.v.y - a.v.x * b.v.z + a.v.y * b.s + a.v.z * b.v.x,
+        a.s * b.v.z + a.v.x * b.v.y - a.v.y * b.v.x + a.v.z * b.s
+    )
+}
+
+pub fn quat_conjugate(q: Quaternion<f32>) -> Quaternion<f32> {
+    Quaternion::new(q.s, -q.v.x, -q.v.y, -q.v.z)
+}
+
+pub fn quat_dot(a: Quaternion<f32>, b: Quaternion<f32>) -> f32 {
+    a.s * b.s + a.v.x * b.v.x + a.v.y * b.v.y + a.v.z * b.v.z
+}
+
+pub fn quat_normalize(q: Quaternion<f32>) -> Quaternion<f32> {
+    let len = quat_dot(q, q).sqrt();
+    if len == 0. {
+        quat_identity()
+    } else {
+        Quaternion::new(q.s / len, q.v.x / len, q.v.y / len, q.v.z / len)
+    }
+}
+
+pub fn quat_rotate(q: Quaternion<f32>, v: Vector3<f32>) -> Vector3<f32> {
+    let p = Quaternion::new(0., v.x, v.y, v.z);
+    let r = quat_mul(quat_mul(q, p), quat_conjugate(q));
+    r.v
+}
+
+// rotation of |v| radians around v
+pub fn quat_from_rotation_vector(v: Vector3<f32>) -> Quaternion<f32> {
+    let angle = vec3_length(v);
+    if angle < 1e-8 {
+        // first order approximation, avoids dividing by a tiny angle
+        quat_normalize(Quaternion::new(1., v.x * 0.5, v.y * 0.5, v.z * 0.5))
+    } else {
+        let s = (angle * 0.5).sin() / angle;
+        Quaternion::new((angle * 0.5).cos(), v.x * s, v.y * s, v.z * s)
+    }
+}
+
+// inverse of quat_from_rotation_vector, takes the short way around
+pub fn quat_to_rotation_vector(q: Quaternion<f32>) -> Vector3<f32> {
+    let q = if q.s < 0. {
+        Quaternion::new(-q.s, -q.v.x, -q.v.y, -q.v.z)
+    } else {
+        q
+    };
+    let sin_half = vec3_length(q.v);
+    if sin_half < 1e-8 {
+        vec3_scale(q.v, 2.)
+    } else {
+        let angle = 2. * sin_half.atan2(q.s);
+        vec3_scale(q.v, angle / sin_half)
+    }
+}
+
+pub fn quat_slerp(a: Quaternion<f32>, b: Quaternion<f32>, t: f32) -> Quaternion<f32> {
+    let delta = quat_mul(quat_conjugate(a), b);
+    let rv = quat_to_rotation_vector(delta);
+    quat_normalize(quat_mul(a, quat_from_rotation_vector(vec3_scale(rv, t))))
+}
+
+// yaw around y, pitch around x, roll around z, applied in that order
+pub fn quat_from_euler(yaw: f32, pitch: f32, roll: f32) -> Quaternion<f32> {
+    let y = quat_from_rotation_vector(Vector3::new(0., yaw, 0.));
+    let p = quat_from_rotation_vector(Vector3::new(pitch, 0., 0.));
+    let r = quat_from_rotation_vector(Vector3::new(0., 0., roll));
+    quat_mul(quat_mul(y, p), r)
+}
+
+// returns (yaw, pitch, roll) matching quat_from_euler
+pub fn quat_to_euler(q: Quaternion<f32>) -> (f32, f32, f32) {
+    let (w, x, y, z) = (q.s, q.v.x, q.v.y, q.v.z);
+    let sin_pitch = 2. * (w * x - y * z);
+    let pitch = if sin_pitch >= 1. {
+        ::std::f32::consts::FRAC_PI_2
+    } else if sin_pitch <= -1. {
+        -::std::f32::consts::FRAC_PI_2
+    } else {
+        sin_pitch.asin()
+    };
+    let yaw = (2. * (w * y + x * z)).atan2(1. - 2. * (x * x + y * y));
+    let roll = (2. * (w * z + x * y)).atan2(1. - 2. * (x * x + z * z));
+    (yaw, pitch, roll)
+}
+
+// rigid transform that rotates by q and then translates by p
+pub fn rigid_to_mat4(q: Quaternion<f32>, p: Vector3<f32>) -> Matrix4<f32> {
+    let (w, x, y, z) = (q.s, q.v.x, q.v.y, q.v.z);
+    Matrix4::new(
+        1. - 2. * (y * y + z * z), 2. * (x * y + w * z), 2. * (x * z - w * y), 0.,
+        2. * (x * y - w * z), 1. - 2. * (x * x + z * z), 2. * (y * z + w * x), 0.,
+        2. * (x * z + w * y), 2. * (y * z - w * x), 1. - 2. * (x * x + y * y), 0.,
+        p.x, p.y, p.z, 1.
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use cgmath::{Quaternion, Vector3};
+
+    use super::{quat_from_euler, quat_to_euler, quat_rotate, quat_from_rotation_vector,
+                quat_to_rotation_vector, quat_slerp, rigid_to_mat4};
+
+    fn assert_close(a: f32, b: f32) {
+        assert!((a - b).abs() < 1e-5, "{} != {}", a, b);
+    }
+
+    fn assert_vec_eq(a: Vector3<f32>, b: Vector3<f32>) {
+        assert_close(a.x, b.x);
+        assert_close(a.y, b.y);
+        assert_close(a.z, b.z);
+    }
+
+    // the rotation matrices of the three angles as rows, multiplied onto v
+    fn rotate_y(a: f32, v: Vector3<f32>) -> Vector3<f32> {
+        Vector3::new(a.cos() * v.x + a.sin() * v.z, v.y, -a.sin() * v.x + a.cos() * v.z)
+    }
+
+    fn rotate_x(a: f32, v: Vector3<f32>) -> Vector3<f32> {
+        Vector3::new(v.x, a.cos() * v.y - a.sin() * v.z, a.sin() * v.y + a.cos() * v.z)
+    }
+
+    fn rotate_z(a: f32, v: Vector3<f32>) -> Vector3<f32> {
+        Vector3::new(a.cos() * v.x - a.sin() * v.y, a.sin() * v.x + a.cos() * v.y, v.z)
+    }
+
+    const ANGLES: [(f32, f32, f32); 5] = [
+        (0., 0., 0.),
+        (0.5, 0., 0.),
+        (0.3, -0.4, 0.2),
+        (-2.5, 1.2, -0.7),
+        (3., -1.5, 2.9)
+    ];
+
+    #[test]
+    fn euler_is_yaw_then_pitch_then_roll() {
+        let v = Vector3::new(0.3, -0.2, -1.);
+        for &(yaw, pitch, roll) in ANGLES.iter() {
+            let expected = rotate_y(yaw, rotate_x(pitch, rotate_z(roll, v)));
+            assert_vec_eq(quat_rotate(quat_from_euler(yaw, pitch, roll), v), expected);
+        }
+
+        // a quarter turn of yaw turns forward to the left
+        let left = quat_rotate(quat_from_euler(::std::f32::consts::FRAC_PI_2, 0., 0.),
+                               Vector3::new(0., 0., -1.));
+        assert_vec_eq(left, Vector3::new(-1., 0., 0.));
+    }
+
+    #[test]
+    fn euler_round_trips() {
+        for &(yaw, pitch, roll) in ANGLES.iter() {
+            let (y, p, r) = quat_to_euler(quat_from_euler(yaw, pitch, roll));
+            assert_close(y, yaw);
+            assert_close(p, pitch);
+            assert_close(r, roll);
+        }
+    }
+
+    #[test]
+    fn rotation_vectors_round_trip() {
+        for &v in [Vector3::new(0., 0., 0.), Vector3::new(1e-9, 0., 0.),
+                   Vector3::new(0.3, -1.2, 0.5), Vector3::new(0., 3., 0.)].iter() {
+            assert_vec_eq(quat_to_rotation_vector(quat_from_rotation_vector(v)), v);
+        }
+
+        // the negated quaternion is the same rotation
+        let q = quat_from_rotation_vector(Vector3::new(0.3, -1.2, 0.5));
+        let neg = Quaternion::new(-q.s, -q.v.x, -q.v.y, -q.v.z);
+        assert_vec_eq(quat_to_rotation_vector(neg), Vector3::new(0.3, -1.2, 0.5));
+    }
+
+    #[test]
+    fn slerp_moves_at_constant_rate() {
+        let a = quat_from_euler(0.2, 0., 0.);
+        let b = quat_from_euler(1.2, 0., 0.);
+        for &t in [0., 0.25, 0.5, 1.].iter() {
+            let (yaw, _, _) = quat_to_euler(quat_slerp(a, b, t));
+            assert_close(yaw, 0.2 + t);
+        }
+    }
+
+    #[test]
+    fn rigid_to_mat4_rotates_then_translates() {
+        let q = quat_from_euler(0.3, -0.4, 0.2);
+        let p = Vector3::new(1., 2., -3.);
+        let m = rigid_to_mat4(q, p);
+        let v = Vector3::new(0.5, -0.25, 2.);
+
+        // cgmath matrices are column major, m.w is the translation
+        let transformed = Vector3::new(m.x.x * v.x + m.y.x * v.y + m.z.x * v.z + m.w.x,
+                                       m.x.y * v.x + m.y.y * v.y + m.z.y * v.z + m.w.y,
+                                       m.x.z * v.x + m.y.z * v.y + m.z.z * v.z + m.w.z);
+        let r = quat_rotate(q, v);
+        assert_vec_eq(transformed, Vector3::new(r.x + p.x, r.y + p.y, r.z + p.z));
+        assert_eq!((m.x.w, m.y.w, m.z.w, m.w.w), (0., 0., 0., 1.));
+    }
+}
@@ -0,0 +1,338 @@
+// Polling of a SensorSource on its own thread, so the tracking rate does not
+// depend on the frame rate of whoever reads it.
+//
+// The newest sample and a bounded history of past samples are kept behind
+// mutexes, they are only held for a copy so readers and the polling thread
+// barely contend at the rates a sensor runs at. Every sample can be pushed
+// into an mpsc channel as well.
+//
+// The rate is kept with the monotonic wall clock of the time crate, separate
+// from the clock the source is asked for samples with.
+
+use std::collections::VecDeque;
+use std::mem;
+use std::sync::{Arc, Mutex};
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::mpsc::Sender;
+use std::thread::{self, JoinHandle};
+
+use time;
+
+#[cfg(feature = "sdk")]
+use super::get_time;
+use super::{SensorSource, SensorState};
+
+pub struct PollerOptions {
+    rate: f64,
+    history: usize,
+    channel: Option<Sender<SensorState>>,
+    clock: fn() -> f64
+}
+
+impl PollerOptions {
+    // 500 Hz, 1000 samples of history, no channel and the SDK clock
+    pub fn new() -> PollerOptions {
+        PollerOptions {
+            rate: 500.,
+            history: 1000,
+            channel: None,
+            clock: default_clock
+        }
+    }
+
+    // samples per second
+    pub fn rate(mut self, rate: f64) -> PollerOptions {
+        self.rate = rate; self
+    }
+
+    // number of samples kept by history(), 0 keeps none
+    pub fn history(mut self, samples: usize) -> PollerOptions {
+        self.history = samples; self
+    }
+
+    // every new sample is also sent here, sending stops once the
+    // receiver is dropped
+    pub fn channel(mut self, sender: Sender<SensorState>) -> PollerOptions {
+        self.channel = Some(sender); self
+    }
+
+    // the absolute time passed to the source, defaults to ovr::get_time
+    // when built with the sdk feature and to time::precise_time_s without
+    pub fn clock(mut self, clock: fn() -> f64) -> PollerOptions {
+        self.clock = clock; self
+    }
+}
+
+#[cfg(feature = "sdk")]
+fn default_clock() -> f64 {
+    get_time()
+}
+
+#[cfg(not(feature = "sdk"))]
+fn default_clock() -> f64 {
+    time::precise_time_s()
+}
+
+struct Shared {
+    latest: Mutex<SensorState>,
+    history: Mutex<VecDeque<SensorState>>,
+    capacity: usize,
+    samples: AtomicUsize,
+    running: AtomicBool
+}
+
+impl Shared {
+    fn publish(&self, state: &SensorState) {
+        *self.latest.lock().unwrap() = *state;
+        if self.capacity > 0 {
+            let mut history = self.history.lock().unwrap();
+            if history.len() == self.capacity {
+                history.pop_front();
+            }
+            history.push_back(*state);
+        }
+        self.samples.fetch_add(1, Ordering::Release);
+    }
+}
+
+// A cheap handle to the samples of a SensorPoller that can be cloned and
+// handed to other threads
+#[derive(Clone)]
+pub struct SensorReader {
+    shared: Arc<Shared>
+}
+
+impl SensorReader {
+    // the newest sample
+    pub fn latest(&self) -> SensorState {
+        *self.shared.latest.lock().unwrap()
+    }
+
+    // the kept samples, oldest first
+    pub fn history(&self) -> Vec<SensorState> {
+        self.shared.history.lock().unwrap().iter().map(|s| *s).collect()
+    }
+
+    // number of samples published, including the one taken by start
+    pub fn sample_count(&self) -> usize {
+        self.shared.samples.load(Ordering::Acquire)
+    }
+
+    // false once the poller was stopped
+    pub fn is_running(&self) -> bool {
+        self.shared.running.load(Ordering::Acquire)
+    }
+}
+
+// Owns the polling thread, it is stopped and joined when the poller is
+// dropped. start takes an owned source shared with the thread through an
+// Arc, the thread holds its own clone so the source outlives it even if the
+// poller is leaked. scoped polls a borrowed source, such as a TrackingHmd
+// which borrows its Ovr, for as long as a closure runs.
+//
+// The first sample is taken before the thread runs, so there is always a
+// latest sample.
+pub struct SensorPoller {
+    reader: SensorReader,
+    thread: Option<JoinHandle<()>>
+}
+
+impl SensorPoller {
+    pub fn start<S>(source: Arc<S>, options: PollerOptions) -> SensorPoller
+        where S: SensorSource + Send + Sync + 'static {
+
+        let shared = first_sample(&*source, &options);
+        let thread_shared = shared.clone();
+        let handle = thread::spawn(move || {
+            poll(&*source, &thread_shared, options);
+        });
+
+        SensorPoller {
+            reader: SensorReader { shared: shared },
+            thread: Some(handle)
+        }
+    }
+
+    // polls source on a thread while f runs and returns what f returned.
+    // The poller is only lent to f, it is stopped and joined before scoped
+    // returns or unwinds, so the thread never outlives the borrow.
+    pub fn scoped<'a, S, F, R>(source: &'a S, options: PollerOptions, f: F) -> R
+        where S: SensorSource + Sync + 'a, F: FnOnce(&SensorPoller) -> R {
+
+        let shared = first_sample(source, &options);
+        let thread_shared = shared.clone();
+
+        // the thread only sees the source as 'static, the poller below owns
+        // it and joins it in drop, which runs before the borrow can end since
+        // f never gets the poller by value
+        let source: &'a (SensorSource + Sync + 'a) = source;
+        let source: &'static (SensorSource + Sync + 'static) = unsafe {
+            mem::transmute(source)
+        };
+        let handle = thread::spawn(move || {
+            poll(source, &thread_shared, options);
+        });
+
+        let poller = SensorPoller {
+            reader: SensorReader { shared: shared },
+            thread: Some(handle)
+        };
+        f(&poller)
+    }
+
+    pub fn reader(&self) -> SensorReader {
+        self.reader.clone()
+    }
+
+    pub fn latest(&self) -> SensorState {
+        self.reader.latest()
+    }
+
+    pub fn history(&self) -> Vec<SensorState> {
+        self.reader.history()
+    }
+
+    pub fn sample_count(&self) -> usize {
+        self.reader.sample_count()
+    }
+}
+
+impl Drop for SensorPoller {
+    fn drop(&mut self) {
+        self.reader.shared.running.store(false, Ordering::Release);
+        if let Some(thread) = self.thread.take() {
+            let _ = thread.join();
+        }
+    }
+}
+
+fn first_sample<S: ?Sized + SensorSource>(source: &S, options: &PollerOptions) -> Arc<Shared> {
+    let first = source.get_sensor_state((options.clock)());
+    let shared = Arc::new(Shared {
+        latest: Mutex::new(first),
+        history: Mutex::new(VecDeque::with_capacity(options.history)),
+        capacity: options.history,
+        samples: AtomicUsize::new(0),
+        running: AtomicBool::new(true)
+    });
+    shared.publish(&first);
+    if let Some(ref sender) = options.channel {
+        let _ = sender.send(first);
+    }
+    shared
+}
+
+fn poll<S: ?Sized + SensorSource>(source: &S, shared: &Shared, options: PollerOptions) {
+    let interval = 1. / options.rate;
+    let mut channel = options.channel;
+    let mut last_time = shared.latest.lock().unwrap().recorded.time_in_seconds;
+    let mut next = time::precise_time_s() + interval;
+
+    while shared.running.load(Ordering::Acquire) {
+        let now = time::precise_time_s();
+        if next > now {
+            // never longer than one interval, so a stop is seen quickly, and
+            // at least a millisecond so rates above 1000 Hz do not spin
+            let wait = (next - now).min(interval);
+            thread::sleep_ms(((wait * 1000.) as u32).max(1));
+        }
+        // a late poll is not caught up on
+        next = next.max(now) + interval;
+
+        let state = source.get_sensor_state((options.clock)());
+
+        // polling faster than the sensor updates returns the same sample
+        let recorded = state.recorded.time_in_seconds;
+        if recorded > last_time {
+            last_time = recorded;
+            shared.publish(&state);
+
+            let disconnected = match channel {
+                Some(ref sender) => sender.send(state).is_err(),
+                None => false
+            };
+            if disconnected {
+                channel = None;
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::thread;
+
+    use cgmath::{Quaternion, Vector3};
+
+    use super::{PollerOptions, SensorPoller};
+    use super::super::{Pose, PoseState, SensorSource, SensorState, Status};
+
+    // a source borrowing its tick counter, every poll is a new sample
+    struct Ticks<'a> {
+        ticks: &'a AtomicUsize
+    }
+
+    impl<'a> SensorSource for Ticks<'a> {
+        fn get_sensor_state(&self, _: f64) -> SensorState {
+            let zero = Vector3::new(0., 0., 0.);
+            let state = PoseState {
+                pose: Pose {
+                    orientation: Quaternion::new(1., 0., 0., 0.),
+                    position: zero
+                },
+                angular_velocity: zero,
+                linear_velocity: zero,
+                angular_acceleration: zero,
+                linear_acceleration: zero,
+                time_in_seconds: self.ticks.fetch_add(1, Ordering::SeqCst) as f64
+            };
+            SensorState {
+                predicted: state,
+                recorded: state,
+                temperature: 0.,
+                status_flags: Status { flags: 0 }
+            }
+        }
+    }
+
+    #[test]
+    fn scoped_polls_a_borrowed_source_until_it_returns() {
+        let ticks = AtomicUsize::new(0);
+        let source = Ticks { ticks: &ticks };
+
+        let count = SensorPoller::scoped(&source, PollerOptions::new().rate(1000.), |poller| {
+            for _ in 0..1000 {
+                if poller.sample_count() >= 5 {
+                    break;
+                }
+                thread::sleep_ms(1);
+            }
+            poller.sample_count()
+        });
+        assert!(count >= 5);
+
+        // the thread was joined, nothing polls the source any more
+        let stopped = ticks.load(Ordering::SeqCst);
+        thread::sleep_ms(10);
+        assert_eq!(ticks.load(Ordering::SeqCst), stopped);
+    }
+
+    #[test]
+    fn latest_is_the_newest_sample() {
+        let ticks = AtomicUsize::new(0);
+        let source = Ticks { ticks: &ticks };
+
+        SensorPoller::scoped(&source, PollerOptions::new().rate(1000.).history(0), |poller| {
+            let first = poller.latest().recorded.time_in_seconds;
+            for _ in 0..1000 {
+                if poller.latest().recorded.time_in_seconds > first {
+                    break;
+                }
+                thread::sleep_ms(1);
+            }
+            assert!(poller.latest().recorded.time_in_seconds > first);
+            assert!(poller.history().is_empty());
+        });
+    }
+}
@@ -0,0 +1,271 @@
+// Pose prediction done in rust from the derivatives a PoseState carries,
+// so recorded, simulated and live states are all predicted the same way
+// whatever the runtime would have done.
+//
+// Angular velocity and acceleration are in the head's frame like the SDK's
+// gyro readings, the orientation is advanced by right multiplying with the
+// rotation integrated over the interval. Linear velocity and acceleration
+// are in tracking space.
+
+use cgmath::{Quaternion, Vector3};
+
+use math::{vec3_add, vec3_scale, quat_mul, quat_normalize, quat_from_rotation_vector};
+use super::{Pose, PoseState};
+
+// the longest step used when the angular velocity changes over the
+// interval, and a bound on the number of steps for very long predictions
+const MAX_STEP: f64 = 0.002;
+const MAX_STEPS: usize = 1000;
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum Predictor {
+    // velocities stay as they are, accelerations are ignored
+    ConstantVelocity,
+    // velocities change with the accelerations
+    ConstantAcceleration,
+    // velocities decay exponentially, after time_constant seconds they
+    // are down to 1/e, this keeps long predictions from overshooting
+    Damped { time_constant: f64 }
+}
+
+impl Predictor {
+    // the state dt seconds after state, dt may be negative
+    pub fn predict(&self, state: &PoseState, dt: f64) -> PoseState {
+        match *self {
+            Predictor::ConstantVelocity => constant_velocity(state, dt),
+            Predictor::ConstantAcceleration => constant_acceleration(state, dt),
+            Predictor::Damped { time_constant } => damped(state, dt, time_constant)
+        }
+    }
+}
+
+fn rotate(q: Quaternion<f32>, rotation: Vector3<f32>) -> Quaternion<f32> {
+    quat_normalize(quat_mul(q, quat_from_rotation_vector(rotation)))
+}
+
+fn constant_velocity(state: &PoseState, dt: f64) -> PoseState {
+    let t = dt as f32;
+    let zero = Vector3::new(0., 0., 0.);
+
+    PoseState {
+        pose: Pose {
+            orientation: rotate(state.pose.orientation, vec3_scale(state.angular_velocity, t)),
+            position: vec3_add(state.pose.position, vec3_scale(state.linear_velocity, t))
+        },
+        angular_velocity: state.angular_velocity,
+        linear_velocity: state.linear_velocity,
+        angular_acceleration: zero,
+        linear_acceleration: zero,
+        time_in_seconds: state.time_in_seconds + dt
+    }
+}
+
+fn constant_acceleration(state: &PoseState, dt: f64) -> PoseState {
+    // the axis of rotation moves when the angular acceleration is not
+    // parallel to the velocity, so the orientation is integrated in short
+    // steps using the velocity at the middle of each
+    let steps = ((dt.abs() / MAX_STEP).ceil() as usize).max(1).min(MAX_STEPS);
+    let h = (dt / steps as f64) as f32;
+    let mut orientation = state.pose.orientation;
+    for i in 0..steps {
+        let mid = (i as f32 + 0.5) * h;
+        let w = vec3_add(state.angular_velocity, vec3_scale(state.angular_acceleration, mid));
+        orientation = rotate(orientation, vec3_scale(w, h));
+    }
+
+    let t = dt as f32;
+    let translation = vec3_add(vec3_scale(state.linear_velocity, t),
+                               vec3_scale(state.linear_acceleration, 0.5 * t * t));
+
+    PoseState {
+        pose: Pose {
+            orientation: orientation,
+            position: vec3_add(state.pose.position, translation)
+        },
+        angular_velocity: vec3_add(state.angular_velocity,
+                                   vec3_scale(state.angular_acceleration, t)),
+        linear_velocity: vec3_add(state.linear_velocity,
+                                  vec3_scale(state.linear_acceleration, t)),
+        angular_acceleration: state.angular_acceleration,
+        linear_acceleration: state.linear_acceleration,
+        time_in_seconds: state.time_in_seconds + dt
+    }
+}
+
+fn damped(state: &PoseState, dt: f64, time_constant: f64) -> PoseState {
+    if time_constant <= 0. {
+        return constant_velocity(&PoseState {
+            angular_velocity: Vector3::new(0., 0., 0.),
+            linear_velocity: Vector3::new(0., 0., 0.),
+            .. *state
+        }, dt);
+    }
+
+    // v(t) = v * e^(-t / tau), so the distance covered is v * tau * (1 - e^(-t / tau))
+    let decay = (-dt / time_constant).exp();
+    let covered = (time_constant * (1. - decay)) as f32;
+    let decay = decay as f32;
+    let angular_velocity = vec3_scale(state.angular_velocity, decay);
+    let linear_velocity = vec3_scale(state.linear_velocity, decay);
+    let rate = -1. / time_constant as f32;
+
+    PoseState {
+        pose: Pose {
+            orientation: rotate(state.pose.orientation, vec3_scale(state.angular_velocity, covered)),
+            position: vec3_add(state.pose.position, vec3_scale(state.linear_velocity, covered))
+        },
+        angular_velocity: angular_velocity,
+        linear_velocity: linear_velocity,
+        angular_acceleration: vec3_scale(angular_velocity, rate),
+        linear_acceleration: vec3_scale(linear_velocity, rate),
+        time_in_seconds: state.time_in_seconds + dt
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use cgmath::{Quaternion, Vector3};
+
+    use math::quat_mul;
+    use super::Predictor;
+    use super::super::{Pose, PoseState};
+
+    // rotation of angle radians around axis, written out rather than going
+    // through the helpers under test
+    fn axis_angle(axis: Vector3<f32>, angle: f32) -> Quaternion<f32> {
+        let len = (axis.x * axis.x + axis.y * axis.y + axis.z * axis.z).sqrt();
+        let s = (angle * 0.5).sin() / len;
+        Quaternion::new((angle * 0.5).cos(), axis.x * s, axis.y * s, axis.z * s)
+    }
+
+    // q and -q are the same rotation
+    fn assert_quat_eq(a: Quaternion<f32>, b: Quaternion<f32>, eps: f32) {
+        let d = a.s * b.s + a.v.x * b.v.x + a.v.y * b.v.y + a.v.z * b.v.z;
+        assert!(1. - d.abs() < eps, "{:?} != {:?}", a, b);
+    }
+
+    fn assert_vec_eq(a: Vector3<f32>, b: Vector3<f32>, eps: f32) {
+        assert!((a.x - b.x).abs() < eps && (a.y - b.y).abs() < eps && (a.z - b.z).abs() < eps,
+                "{:?} != {:?}", a, b);
+    }
+
+    fn state(angular_velocity: Vector3<f32>, angular_acceleration: Vector3<f32>,
+             linear_velocity: Vector3<f32>, linear_acceleration: Vector3<f32>) -> PoseState {
+        PoseState {
+            pose: Pose {
+                // a quarter turn of yaw, so the head and tracking frames differ
+                orientation: axis_angle(Vector3::new(0., 1., 0.), 1.5707964),
+                position: Vector3::new(0.1, 1.6, -0.3)
+            },
+            angular_velocity: angular_velocity,
+            linear_velocity: linear_velocity,
+            angular_acceleration: angular_acceleration,
+            linear_acceleration: linear_acceleration,
+            time_in_seconds: 10.
+        }
+    }
+
+    #[test]
+    fn constant_angular_velocity_is_a_fixed_axis_rotation() {
+        let zero = Vector3::new(0., 0., 0.);
+        let w = Vector3::new(0.3, -1.2, 0.5);
+        let s = state(w, zero, Vector3::new(0.2, 0., -0.1), zero);
+        let dt = 0.05;
+
+        let p = Predictor::ConstantVelocity.predict(&s, dt);
+        let speed = (w.x * w.x + w.y * w.y + w.z * w.z).sqrt();
+        assert_quat_eq(p.pose.orientation,
+                       quat_mul(s.pose.orientation, axis_angle(w, speed * dt as f32)), 1e-6);
+        assert_vec_eq(p.pose.position, Vector3::new(0.11, 1.6, -0.305), 1e-6);
+        assert_eq!(p.time_in_seconds, 10.05);
+
+        // and back again
+        let back = Predictor::ConstantVelocity.predict(&p, -dt);
+        assert_quat_eq(back.pose.orientation, s.pose.orientation, 1e-6);
+        assert_vec_eq(back.pose.position, s.pose.position, 1e-6);
+    }
+
+    #[test]
+    fn angular_velocity_is_in_the_head_frame() {
+        // the SDK's sensor fusion predicts with
+        // Rotation * Quatf(AngularVelocity, angularSpeed * dt), a rotation
+        // around the head's own axes applied on the right
+        let zero = Vector3::new(0., 0., 0.);
+        let w = Vector3::new(2., 0., 0.);
+        let s = state(w, zero, zero, zero);
+        let dt = 0.1;
+
+        let p = s.predict(dt);
+        let step = axis_angle(w, 2. * dt as f32);
+        assert_quat_eq(p.pose.orientation, quat_mul(s.pose.orientation, step), 1e-6);
+
+        // pitching in tracking space would be a different rotation, the
+        // head is turned a quarter around y
+        let world = quat_mul(step, s.pose.orientation);
+        let d = p.pose.orientation.s * world.s + p.pose.orientation.v.x * world.v.x +
+                p.pose.orientation.v.y * world.v.y + p.pose.orientation.v.z * world.v.z;
+        assert!(1. - d.abs() > 1e-3);
+    }
+
+    #[test]
+    fn constant_acceleration_matches_the_analytic_solution() {
+        let axis = Vector3::new(0.48, 0.6, 0.64);
+        let w = Vector3::new(axis.x * 0.5, axis.y * 0.5, axis.z * 0.5);
+        let a = Vector3::new(axis.x * 3., axis.y * 3., axis.z * 3.);
+        let v = Vector3::new(0.2, -0.1, 0.4);
+        let la = Vector3::new(0., -9.8, 1.);
+        let s = state(w, a, v, la);
+        let dt = 0.05f32;
+
+        let p = Predictor::ConstantAcceleration.predict(&s, dt as f64);
+
+        // the axis is fixed when the acceleration is parallel to the velocity,
+        // the angle is then w t + a t^2 / 2
+        let angle = 0.5 * dt + 0.5 * 3. * dt * dt;
+        assert_quat_eq(p.pose.orientation, quat_mul(s.pose.orientation, axis_angle(axis, angle)), 1e-6);
+        assert_vec_eq(p.angular_velocity,
+                      Vector3::new(w.x + a.x * dt, w.y + a.y * dt, w.z + a.z * dt), 1e-6);
+
+        let expected = Vector3::new(s.pose.position.x + v.x * dt + 0.5 * la.x * dt * dt,
+                                    s.pose.position.y + v.y * dt + 0.5 * la.y * dt * dt,
+                                    s.pose.position.z + v.z * dt + 0.5 * la.z * dt * dt);
+        assert_vec_eq(p.pose.position, expected, 1e-6);
+        assert_vec_eq(p.linear_velocity,
+                      Vector3::new(v.x + la.x * dt, v.y + la.y * dt, v.z + la.z * dt), 1e-6);
+    }
+
+    #[test]
+    fn damped_converges() {
+        let zero = Vector3::new(0., 0., 0.);
+        let w = Vector3::new(0., 1., 0.);
+        let v = Vector3::new(1., 0., -2.);
+        let s = state(w, zero, v, zero);
+        let tau = 0.1;
+        let predictor = Predictor::Damped { time_constant: tau };
+
+        // the head comes to rest v tau away from where it was
+        let rest = Vector3::new(s.pose.position.x + v.x * tau as f32,
+                                s.pose.position.y + v.y * tau as f32,
+                                s.pose.position.z + v.z * tau as f32);
+        let far = predictor.predict(&s, 20. * tau);
+        assert_vec_eq(far.pose.position, rest, 1e-5);
+        assert_vec_eq(far.linear_velocity, zero, 1e-5);
+        assert_quat_eq(far.pose.orientation,
+                       quat_mul(s.pose.orientation, axis_angle(w, tau as f32)), 1e-6);
+
+        // and gets closer the further ahead it is predicted
+        let mut last = ::std::f32::MAX;
+        for i in 1..10 {
+            let p = predictor.predict(&s, i as f64 * tau);
+            let d = p.pose.position;
+            let left = ((d.x - rest.x).powi(2) + (d.y - rest.y).powi(2) + (d.z - rest.z).powi(2)).sqrt();
+            assert!(left < last);
+            last = left;
+        }
+
+        // a short prediction moves like constant velocity
+        let near = predictor.predict(&s, 1e-4);
+        let constant = Predictor::ConstantVelocity.predict(&s, 1e-4);
+        assert_vec_eq(near.pose.position, constant.pose.position, 1e-6);
+    }
+}
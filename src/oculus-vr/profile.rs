@@ -0,0 +1,369 @@
+// A user's physical measurements kept apart from the SDK's profile store,
+// for headsets shared by many people.
+//
+// A profile is a text file of "key = value" lines, blank lines and lines
+// starting with # are ignored:
+//
+//     name = Alice
+//     ipd = 0.0615
+//     eye_height = 1.62
+//     neck_to_eye_horizontal = 0.11
+//     neck_to_eye_vertical = 0.12
+//     eye_relief = 0.014
+//
+// Distances are in meters, keys that are left out keep the SDK's defaults.
+// Backslashes and line breaks in the name are escaped as \\, \n and \r.
+
+use std::error;
+use std::fmt;
+use std::fs::File;
+use std::io::{self, BufRead, BufReader, BufWriter, Write};
+use std::path::Path;
+
+use cgmath::{Quaternion, Vector2, Vector3};
+
+use math::{vec3_add, vec3_sub, quat_rotate};
+use properties::{DEFAULT_IPD, DEFAULT_EYE_HEIGHT, DEFAULT_NECK_TO_EYE_HORIZONTAL,
+                 DEFAULT_NECK_TO_EYE_VERTICAL};
+use super::{Eye, EyeRenderDescriptor, Pose};
+#[cfg(feature = "sdk")]
+use super::{Error, Hmd};
+
+#[derive(Debug)]
+pub enum ProfileError {
+    Io(io::Error),
+    // a line that is not "key = value"
+    BadLine(usize),
+    UnknownKey(usize, String),
+    BadValue(usize)
+}
+
+impl error::Error for ProfileError {
+    fn description(&self) -> &str {
+        match *self {
+            ProfileError::Io(ref err) => err.description(),
+            ProfileError::BadLine(_) => "expected key = value",
+            ProfileError::UnknownKey(_, _) => "unknown profile key",
+            ProfileError::BadValue(_) => "malformed value"
+        }
+    }
+
+    fn cause(&self) -> Option<&error::Error> {
+        match *self {
+            ProfileError::Io(ref err) => Some(err),
+            _ => None
+        }
+    }
+}
+
+impl fmt::Display for ProfileError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            ProfileError::Io(ref err) => write!(f, "{}", err),
+            ProfileError::BadLine(line) => write!(f, "expected key = value on line {}", line),
+            ProfileError::UnknownKey(line, ref key) =>
+                write!(f, "unknown profile key {} on line {}", key, line),
+            ProfileError::BadValue(line) => write!(f, "malformed value on line {}", line)
+        }
+    }
+}
+
+impl From<io::Error> for ProfileError {
+    fn from(err: io::Error) -> ProfileError {
+        ProfileError::Io(err)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct UserProfile {
+    pub name: String,
+    // distance between the pupils
+    pub ipd: f32,
+    // height of the eyes above the floor when standing
+    pub eye_height: f32,
+    // offset of the center between the eyes from the neck's pivot, forward
+    // and up, the neck model rotates the eyes around that pivot
+    pub neck_to_eye: Vector2<f32>,
+    // distance from the eyes to the lenses, None when not measured. The
+    // 0.3.2 SDK has no property for it, it is kept for the application.
+    pub eye_relief: Option<f32>
+}
+
+impl UserProfile {
+    // a profile with the SDK's default measurements
+    pub fn new(name: &str) -> UserProfile {
+        UserProfile {
+            name: name.to_string(),
+            ipd: DEFAULT_IPD,
+            eye_height: DEFAULT_EYE_HEIGHT,
+            neck_to_eye: Vector2::new(DEFAULT_NECK_TO_EYE_HORIZONTAL, DEFAULT_NECK_TO_EYE_VERTICAL),
+            eye_relief: None
+        }
+    }
+
+    // the measurements from the SDK's profile currently in use
+    #[cfg(feature = "sdk")]
+    pub fn from_hmd(hmd: &Hmd) -> UserProfile {
+        UserProfile {
+            name: hmd.user_name(),
+            ipd: hmd.ipd(),
+            eye_height: hmd.eye_height(),
+            neck_to_eye: hmd.neck_to_eye_distance(),
+            eye_relief: None
+        }
+    }
+
+    pub fn open<P: AsRef<Path>>(path: P) -> Result<UserProfile, ProfileError> {
+        let file = try!(File::open(path));
+        UserProfile::read(BufReader::new(file))
+    }
+
+    pub fn read<R: BufRead>(input: R) -> Result<UserProfile, ProfileError> {
+        let mut profile = UserProfile::new("");
+
+        for (i, line) in input.lines().enumerate() {
+            let line_number = i + 1;
+            let line = try!(line);
+            let line = line.trim();
+            if line.len() == 0 || line.starts_with("#") {
+                continue;
+            }
+
+            let mut parts = line.splitn(2, '=');
+            let (key, value) = match (parts.next(), parts.next()) {
+                (Some(key), Some(value)) => (key.trim(), value.trim()),
+                _ => return Err(ProfileError::BadLine(line_number))
+            };
+
+            if key == "name" {
+                profile.name = match unescape(value) {
+                    Some(name) => name,
+                    None => return Err(ProfileError::BadValue(line_number))
+                };
+                continue;
+            }
+
+            let value = match value.parse::<f32>() {
+                Ok(value) => value,
+                Err(_) => return Err(ProfileError::BadValue(line_number))
+            };
+            match key {
+                "ipd" => profile.ipd = value,
+                "eye_height" => profile.eye_height = value,
+                "neck_to_eye_horizontal" => profile.neck_to_eye.x = value,
+                "neck_to_eye_vertical" => profile.neck_to_eye.y = value,
+                "eye_relief" => profile.eye_relief = Some(value),
+                _ => return Err(ProfileError::UnknownKey(line_number, key.to_string()))
+            }
+        }
+
+        Ok(profile)
+    }
+
+    pub fn save<P: AsRef<Path>>(&self, path: P) -> io::Result<()> {
+        let mut out = BufWriter::new(try!(File::create(path)));
+        try!(self.write(&mut out));
+        out.flush()
+    }
+
+    pub fn write<W: Write>(&self, out: &mut W) -> io::Result<()> {
+        try!(writeln!(out, "name = {}", escape(&self.name)));
+        try!(writeln!(out, "ipd = {}", self.ipd));
+        try!(writeln!(out, "eye_height = {}", self.eye_height));
+        try!(writeln!(out, "neck_to_eye_horizontal = {}", self.neck_to_eye.x));
+        try!(writeln!(out, "neck_to_eye_vertical = {}", self.neck_to_eye.y));
+        match self.eye_relief {
+            Some(relief) => writeln!(out, "eye_relief = {}", relief),
+            None => Ok(())
+        }
+    }
+
+    // writes the measurements into the SDK's properties, so the SDK's own
+    // rendering and head model use them
+    #[cfg(feature = "sdk")]
+    pub fn apply(&self, hmd: &Hmd) -> Result<(), Error> {
+        try!(hmd.set_ipd(self.ipd));
+        try!(hmd.set_eye_height(self.eye_height));
+        hmd.set_neck_to_eye_distance(self.neck_to_eye)
+    }
+
+    // the view_adjust for an eye with this profile's ipd
+    pub fn view_adjust(&self, eye: Eye) -> Vector3<f32> {
+        let half = self.ipd * 0.5;
+        match eye {
+            Eye::Left => Vector3::new(half, 0., 0.),
+            Eye::Right => Vector3::new(-half, 0., 0.)
+        }
+    }
+
+    // the render description with the view offset of this profile, the
+    // SDK's reflects the profile it had when rendering was configured
+    pub fn adjust_render_description(&self, desc: &EyeRenderDescriptor) -> EyeRenderDescriptor {
+        EyeRenderDescriptor {
+            view_adjust: self.view_adjust(desc.eye),
+            .. *desc
+        }
+    }
+
+    // moves the position of an orientation only pose as if the head
+    // rotated around the neck, the position is unchanged looking straight
+    // ahead with the head level
+    pub fn apply_neck_model(&self, pose: &Pose) -> Pose {
+        Pose {
+            orientation: pose.orientation,
+            position: vec3_add(pose.position, self.neck_offset(pose.orientation))
+        }
+    }
+
+    fn neck_offset(&self, orientation: Quaternion<f32>) -> Vector3<f32> {
+        // the SDK's head model, forward is -z
+        let neck_to_eye = Vector3::new(0., self.neck_to_eye.y, -self.neck_to_eye.x);
+        vec3_sub(quat_rotate(orientation, neck_to_eye), neck_to_eye)
+    }
+}
+
+fn escape(s: &str) -> String {
+    let mut escaped = String::new();
+    for c in s.chars() {
+        match c {
+            '\\' => escaped.push_str("\\\\"),
+            '\n' => escaped.push_str("\\n"),
+            '\r' => escaped.push_str("\\r"),
+            c => escaped.push(c)
+        }
+    }
+    escaped
+}
+
+// None for an unknown escape or a backslash at the end
+fn unescape(s: &str) -> Option<String> {
+    let mut unescaped = String::new();
+    let mut chars = s.chars();
+    while let Some(c) = chars.next() {
+        if c != '\\' {
+            unescaped.push(c);
+            continue;
+        }
+        match chars.next() {
+            Some('\\') => unescaped.push('\\'),
+            Some('n') => unescaped.push('\n'),
+            Some('r') => unescaped.push('\r'),
+            _ => return None
+        }
+    }
+    Some(unescaped)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::f32::consts::FRAC_PI_2;
+    use std::io::Cursor;
+
+    use cgmath::{Vector2, Vector3};
+
+    use math::{quat_from_euler, quat_identity};
+    use super::{ProfileError, UserProfile};
+    use super::super::{Eye, FovPort, HmdType, Pose};
+    use super::super::simulated::{SimulatedHmd, Stationary};
+
+    fn read(text: &str) -> Result<UserProfile, ProfileError> {
+        UserProfile::read(Cursor::new(text.as_bytes()))
+    }
+
+    fn round_trip(profile: &UserProfile) -> UserProfile {
+        let mut out = Vec::new();
+        profile.write(&mut out).unwrap();
+        UserProfile::read(Cursor::new(out)).unwrap()
+    }
+
+    fn assert_vec_eq(a: Vector3<f32>, b: Vector3<f32>) {
+        assert!((a.x - b.x).abs() < 1e-6 && (a.y - b.y).abs() < 1e-6 && (a.z - b.z).abs() < 1e-6,
+                "{:?} != {:?}", a, b);
+    }
+
+    #[test]
+    fn profiles_round_trip() {
+        let mut profile = UserProfile::new("Alice");
+        profile.ipd = 0.0615;
+        profile.eye_height = 1.62;
+        profile.neck_to_eye = Vector2::new(0.11, 0.12);
+        assert_eq!(round_trip(&profile), profile);
+
+        profile.eye_relief = Some(0.014);
+        assert_eq!(round_trip(&profile), profile);
+    }
+
+    #[test]
+    fn names_with_line_breaks_round_trip() {
+        let profile = UserProfile::new("Alice\nipd = 1\r\\n \\");
+        let mut out = Vec::new();
+        profile.write(&mut out).unwrap();
+        assert_eq!(String::from_utf8(out).unwrap().lines().count(), 5);
+        assert_eq!(round_trip(&profile), profile);
+    }
+
+    #[test]
+    fn reads_comments_and_defaults() {
+        let profile = read("# shared headset\n\nname = Bob\nipd = 0.07\n").unwrap();
+        let mut expected = UserProfile::new("Bob");
+        expected.ipd = 0.07;
+        assert_eq!(profile, expected);
+    }
+
+    #[test]
+    fn rejects_malformed_profiles() {
+        match read("name = Bob\nipd 0.07\n") {
+            Err(ProfileError::BadLine(2)) => (),
+            other => panic!("{:?}", other)
+        }
+        match read("height = 1.8\n") {
+            Err(ProfileError::UnknownKey(1, ref key)) if *key == "height" => (),
+            other => panic!("{:?}", other)
+        }
+        match read("\nipd = wide\n") {
+            Err(ProfileError::BadValue(2)) => (),
+            other => panic!("{:?}", other)
+        }
+        match read("name = Bob\\t\n") {
+            Err(ProfileError::BadValue(1)) => (),
+            other => panic!("{:?}", other)
+        }
+    }
+
+    #[test]
+    fn view_adjust_is_half_the_ipd() {
+        let mut profile = UserProfile::new("Alice");
+        profile.ipd = 0.06;
+        assert_vec_eq(profile.view_adjust(Eye::Left), Vector3::new(0.03, 0., 0.));
+        assert_vec_eq(profile.view_adjust(Eye::Right), Vector3::new(-0.03, 0., 0.));
+
+        let hmd = SimulatedHmd::new(HmdType::DK1, Stationary::new());
+        let fov = FovPort { up: 1., down: 1.1, left: 0.9, right: 1.2 };
+        let desc = hmd.get_render_description(Eye::Right, fov);
+        let adjusted = profile.adjust_render_description(&desc);
+        assert_vec_eq(adjusted.view_adjust, Vector3::new(-0.03, 0., 0.));
+        assert_eq!(adjusted.eye.to_ll(), Eye::Right.to_ll());
+        assert_eq!(adjusted.fov.down, 1.1);
+        assert_eq!(adjusted.distorted_viewport.pos.x, desc.distorted_viewport.pos.x);
+    }
+
+    #[test]
+    fn neck_model_rotates_the_eyes_around_the_neck() {
+        let mut profile = UserProfile::new("Alice");
+        profile.neck_to_eye = Vector2::new(0.1, 0.15);
+        let at = |orientation| Pose { orientation: orientation, position: Vector3::new(1., 2., 3.) };
+
+        // looking straight ahead nothing moves
+        let pose = profile.apply_neck_model(&at(quat_identity()));
+        assert_vec_eq(pose.position, Vector3::new(1., 2., 3.));
+
+        // turned a quarter to the left the eyes swing from -z to -x
+        let pose = profile.apply_neck_model(&at(quat_from_euler(FRAC_PI_2, 0., 0.)));
+        assert_vec_eq(pose.position, Vector3::new(1. - 0.1, 2., 3. + 0.1));
+
+        // looking straight up the eyes end up above the neck and behind it
+        let pose = profile.apply_neck_model(&at(quat_from_euler(0., FRAC_PI_2, 0.)));
+        assert_vec_eq(pose.position, Vector3::new(1., 2. + 0.1 - 0.15, 3. + 0.15 + 0.1));
+        assert_eq!(pose.orientation, quat_from_euler(0., FRAC_PI_2, 0.));
+    }
+}
@@ -0,0 +1,199 @@
+// Projection matrices built in rust, with the options ovrMatrix4f_Projection
+// lacks: reversed depth, an infinite far plane, OpenGL's [-1, 1] depth range
+// and a flipped y axis for Vulkan style clip space.
+//
+// With the defaults the matrix is the same as the SDK's, which maps depth
+// to [0, 1] with the near plane at 0.
+
+use cgmath::Matrix4;
+
+use super::{ll, to_mat4, FovPort};
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum DepthRange {
+    // D3D, Vulkan and the SDK
+    ZeroToOne,
+    // OpenGL without ARB_clip_control
+    MinusOneToOne
+}
+
+#[derive(Debug, Copy, Clone)]
+pub struct Projection {
+    fov: FovPort,
+    znear: f32,
+    zfar: Option<f32>,
+    right_handed: bool,
+    depth_range: DepthRange,
+    reversed_z: bool,
+    flip_y: bool
+}
+
+impl Projection {
+    // a right handed projection with [0, 1] depth like the SDK's
+    pub fn new(fov: FovPort, znear: f32, zfar: f32) -> Projection {
+        Projection {
+            fov: fov,
+            znear: znear,
+            zfar: Some(zfar),
+            right_handed: true,
+            depth_range: DepthRange::ZeroToOne,
+            reversed_z: false,
+            flip_y: false
+        }
+    }
+
+    // puts the far plane at infinity, with reversed_z this gives the most
+    // even depth precision
+    pub fn infinite_far(mut self) -> Projection {
+        self.zfar = None; self
+    }
+
+    pub fn far(mut self, zfar: f32) -> Projection {
+        self.zfar = Some(zfar); self
+    }
+
+    // right handed views look down -z, left handed ones down +z
+    pub fn right_handed(mut self, right_handed: bool) -> Projection {
+        self.right_handed = right_handed; self
+    }
+
+    pub fn depth_range(mut self, depth_range: DepthRange) -> Projection {
+        self.depth_range = depth_range; self
+    }
+
+    // maps the near plane to the far end of the depth range and the far
+    // plane to the near end, use with a greater depth test
+    pub fn reversed_z(mut self, reversed_z: bool) -> Projection {
+        self.reversed_z = reversed_z; self
+    }
+
+    // negates y in clip space, for Vulkan where y points down
+    pub fn flip_y(mut self, flip_y: bool) -> Projection {
+        self.flip_y = flip_y; self
+    }
+
+    pub fn build(&self) -> Matrix4<f32> {
+        let fov = &self.fov;
+        let n = self.znear;
+
+        // scale and offset that map the tangents of the fov to [-1, 1]
+        let x_scale = 2. / (fov.left + fov.right);
+        let x_offset = (fov.left - fov.right) * x_scale * 0.5;
+        let y_scale = 2. / (fov.up + fov.down);
+        let y_offset = (fov.up - fov.down) * y_scale * 0.5;
+
+        // w is the distance in front of the eye, which is -z when right handed
+        let s = if self.right_handed { -1. } else { 1. };
+
+        // depth in clip space as a * distance + b for the [0, 1] range
+        let (a, b) = match (self.zfar, self.reversed_z) {
+            (Some(f), false) => (f / (f - n), -f * n / (f - n)),
+            (Some(f), true) => (-n / (f - n), f * n / (f - n)),
+            (None, false) => (1., -n),
+            (None, true) => (0., n)
+        };
+        let (a, b) = match self.depth_range {
+            DepthRange::ZeroToOne => (a, b),
+            DepthRange::MinusOneToOne => (2. * a - 1., 2. * b)
+        };
+
+        let y = if self.flip_y { -1. } else { 1. };
+
+        to_mat4(ll::Matrix4f {
+            m11: x_scale, m12: 0., m13: s * x_offset, m14: 0.,
+            m21: 0., m22: y * y_scale, m23: -y * s * y_offset, m24: 0.,
+            m31: 0., m32: 0., m33: s * a, m34: b,
+            m41: 0., m42: 0., m43: s, m44: 0.
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use cgmath::Matrix4;
+
+    use super::{DepthRange, Projection};
+    use super::super::FovPort;
+
+    const NEAR: f32 = 0.1;
+    const FAR: f32 = 100.;
+
+    // a DK2 like eye fov, wider towards the nose
+    fn fov() -> FovPort {
+        FovPort { up: 1.33, down: 1.2, left: 1.06, right: 1.29 }
+    }
+
+    // the depth in normalized device coordinates of a point distance in
+    // front of the eye
+    fn depth(m: &Matrix4<f32>, distance: f32, right_handed: bool) -> f32 {
+        let z = if right_handed { -distance } else { distance };
+        let clip_z = m.z.z * z + m.w.z;
+        let clip_w = m.z.w * z + m.w.w;
+        clip_z / clip_w
+    }
+
+    fn assert_close(a: f32, b: f32) {
+        assert!((a - b).abs() < 1e-5, "{} != {}", a, b);
+    }
+
+    #[cfg(feature = "sdk")]
+    fn assert_matrix_eq(a: &Matrix4<f32>, b: &Matrix4<f32>) {
+        let a = [a.x, a.y, a.z, a.w];
+        let b = [b.x, b.y, b.z, b.w];
+        for i in 0..4 {
+            assert_close(a[i].x, b[i].x);
+            assert_close(a[i].y, b[i].y);
+            assert_close(a[i].z, b[i].z);
+            assert_close(a[i].w, b[i].w);
+        }
+    }
+
+    #[cfg(feature = "sdk")]
+    #[test]
+    fn defaults_match_the_sdk() {
+        for &right_handed in [true, false].iter() {
+            let built = Projection::new(fov(), NEAR, FAR).right_handed(right_handed).build();
+            assert_matrix_eq(&built, &fov().projection(NEAR, FAR, right_handed));
+        }
+    }
+
+    #[test]
+    fn depth_is_zero_to_one() {
+        for &right_handed in [true, false].iter() {
+            let m = Projection::new(fov(), NEAR, FAR).right_handed(right_handed).build();
+            assert_close(depth(&m, NEAR, right_handed), 0.);
+            assert_close(depth(&m, FAR, right_handed), 1.);
+        }
+    }
+
+    #[test]
+    fn reversed_depth_is_one_to_zero() {
+        let m = Projection::new(fov(), NEAR, FAR).reversed_z(true).build();
+        assert_close(depth(&m, NEAR, true), 1.);
+        assert_close(depth(&m, FAR, true), 0.);
+    }
+
+    #[test]
+    fn infinite_far_approaches_one() {
+        let m = Projection::new(fov(), NEAR, FAR).infinite_far().build();
+        assert_close(depth(&m, NEAR, true), 0.);
+        assert!(depth(&m, 1e3, true) < depth(&m, 1e6, true));
+        assert_close(depth(&m, 1e6, true), 1.);
+
+        let reversed = Projection::new(fov(), NEAR, FAR).infinite_far().reversed_z(true).build();
+        assert_close(depth(&reversed, NEAR, true), 1.);
+        assert_close(depth(&reversed, 1e6, true), 0.);
+    }
+
+    #[test]
+    fn minus_one_to_one_depth() {
+        let m = Projection::new(fov(), NEAR, FAR).depth_range(DepthRange::MinusOneToOne).build();
+        assert_close(depth(&m, NEAR, true), -1.);
+        assert_close(depth(&m, FAR, true), 1.);
+
+        let reversed = Projection::new(fov(), NEAR, FAR).depth_range(DepthRange::MinusOneToOne)
+                                                         .reversed_z(true).build();
+        assert_close(depth(&reversed, NEAR, true), 1.);
+        assert_close(depth(&reversed, FAR, true), -1.);
+    }
+}
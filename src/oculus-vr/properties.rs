@@ -0,0 +1,201 @@
+// Access to the SDK's hmd properties, which is where the values from the
+// user's profile such as their IPD and eye height are read and changed.
+//
+// Every property has a name, the names the 0.3.2 SDK knows are in KEY_*.
+// Reading a property that does not exist gives the default passed in. The
+// defaults are also available without the sdk feature.
+
+#[cfg(feature = "sdk")]
+use std::ffi::CString;
+
+#[cfg(feature = "sdk")]
+use libc::{c_float, c_uint};
+#[cfg(feature = "sdk")]
+use cgmath::Vector2;
+
+#[cfg(feature = "sdk")]
+use super::{ll, from_buf, Error, Hmd};
+
+pub const KEY_USER: &'static str = "User";
+pub const KEY_NAME: &'static str = "Name";
+pub const KEY_GENDER: &'static str = "Gender";
+pub const KEY_PLAYER_HEIGHT: &'static str = "PlayerHeight";
+pub const KEY_EYE_HEIGHT: &'static str = "EyeHeight";
+pub const KEY_IPD: &'static str = "IPD";
+pub const KEY_NECK_TO_EYE_HORIZONTAL: &'static str = "NeckEyeHori";
+pub const KEY_NECK_TO_EYE_VERTICAL: &'static str = "NeckEyeVert";
+
+// the values the SDK uses when the profile does not set them, in meters
+pub const DEFAULT_GENDER: &'static str = "Male";
+pub const DEFAULT_PLAYER_HEIGHT: f32 = 1.778;
+pub const DEFAULT_EYE_HEIGHT: f32 = 1.675;
+pub const DEFAULT_IPD: f32 = 0.064;
+pub const DEFAULT_NECK_TO_EYE_HORIZONTAL: f32 = 0.12;
+pub const DEFAULT_NECK_TO_EYE_VERTICAL: f32 = 0.12;
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum Gender {
+    Male,
+    Female,
+    Unspecified
+}
+
+#[cfg(feature = "sdk")]
+impl Gender {
+    fn from_str(s: &str) -> Gender {
+        match s {
+            "Male" => Gender::Male,
+            "Female" => Gender::Female,
+            _ => Gender::Unspecified
+        }
+    }
+}
+
+#[cfg(feature = "sdk")]
+fn to_c_name(name: &str) -> Result<CString, Error> {
+    CString::new(name).map_err(|_| Error::InvalidPropertyName(name.to_string()))
+}
+
+#[cfg(feature = "sdk")]
+fn to_c_value(value: &str) -> Result<CString, Error> {
+    CString::new(value).map_err(|_| Error::InvalidPropertyValue(value.to_string()))
+}
+
+#[cfg(feature = "sdk")]
+impl<'a> Hmd<'a> {
+    pub fn get_float(&self, name: &str, default: f32) -> Result<f32, Error> {
+        let name = try!(to_c_name(name));
+        unsafe {
+            Ok(ll::ovrHmd_GetFloat(self.ptr, name.as_ptr(), default as c_float) as f32)
+        }
+    }
+
+    pub fn set_float(&self, name: &str, value: f32) -> Result<(), Error> {
+        let c_name = try!(to_c_name(name));
+        unsafe {
+            if ll::ovrHmd_SetFloat(self.ptr, c_name.as_ptr(), value as c_float) {
+                Ok(())
+            } else {
+                Err(Error::PropertyNotSet(name.to_string()))
+            }
+        }
+    }
+
+    // all values of an array property, empty if it does not exist
+    pub fn get_float_array(&self, name: &str) -> Result<Vec<f32>, Error> {
+        let name = try!(to_c_name(name));
+        unsafe {
+            let size = ll::ovrHmd_GetArraySize(self.ptr, name.as_ptr());
+            let mut values: Vec<f32> = vec![0.; size as usize];
+            if size == 0 {
+                return Ok(values);
+            }
+            let filled = ll::ovrHmd_GetFloatArray(self.ptr, name.as_ptr(),
+                                                  values.as_mut_ptr(), size);
+            values.truncate(filled as usize);
+            Ok(values)
+        }
+    }
+
+    pub fn set_float_array(&self, name: &str, values: &[f32]) -> Result<(), Error> {
+        let c_name = try!(to_c_name(name));
+        // the SDK takes a mutable pointer but only reads from it
+        let mut values = values.to_vec();
+        unsafe {
+            if ll::ovrHmd_SetFloatArray(self.ptr, c_name.as_ptr(),
+                                        values.as_mut_ptr(), values.len() as c_uint) {
+                Ok(())
+            } else {
+                Err(Error::PropertyNotSet(name.to_string()))
+            }
+        }
+    }
+
+    // the string is copied right away, the SDK only keeps its copy until
+    // the next call
+    pub fn get_string(&self, name: &str, default: &str) -> Result<String, Error> {
+        let name = try!(to_c_name(name));
+        let default = try!(to_c_value(default));
+        unsafe {
+            let value = ll::ovrHmd_GetString(self.ptr, name.as_ptr(), default.as_ptr());
+            if value.is_null() {
+                Ok(String::new())
+            } else {
+                Ok(from_buf(value as *const u8))
+            }
+        }
+    }
+
+    // the number of values of a property, 0 if it does not exist
+    pub fn get_array_size(&self, name: &str) -> Result<usize, Error> {
+        let name = try!(to_c_name(name));
+        unsafe {
+            Ok(ll::ovrHmd_GetArraySize(self.ptr, name.as_ptr()) as usize)
+        }
+    }
+
+    pub fn has_property(&self, name: &str) -> bool {
+        self.get_array_size(name).map(|size| size > 0).unwrap_or(false)
+    }
+
+    // the known keys never contain a nul byte, so these can not fail
+
+    fn known_float(&self, name: &str, default: f32) -> f32 {
+        self.get_float(name, default).unwrap_or(default)
+    }
+
+    fn known_string(&self, name: &str, default: &str) -> String {
+        self.get_string(name, default).unwrap_or(default.to_string())
+    }
+
+    // distance between the user's pupils in meters
+    pub fn ipd(&self) -> f32 {
+        self.known_float(KEY_IPD, DEFAULT_IPD)
+    }
+
+    pub fn set_ipd(&self, ipd: f32) -> Result<(), Error> {
+        self.set_float(KEY_IPD, ipd)
+    }
+
+    // height of the user's eyes above the floor when standing in meters
+    pub fn eye_height(&self) -> f32 {
+        self.known_float(KEY_EYE_HEIGHT, DEFAULT_EYE_HEIGHT)
+    }
+
+    pub fn set_eye_height(&self, height: f32) -> Result<(), Error> {
+        self.set_float(KEY_EYE_HEIGHT, height)
+    }
+
+    pub fn player_height(&self) -> f32 {
+        self.known_float(KEY_PLAYER_HEIGHT, DEFAULT_PLAYER_HEIGHT)
+    }
+
+    pub fn set_player_height(&self, height: f32) -> Result<(), Error> {
+        self.set_float(KEY_PLAYER_HEIGHT, height)
+    }
+
+    // the offset of the eyes from the neck's pivot as (horizontal, vertical)
+    pub fn neck_to_eye_distance(&self) -> Vector2<f32> {
+        Vector2::new(self.known_float(KEY_NECK_TO_EYE_HORIZONTAL, DEFAULT_NECK_TO_EYE_HORIZONTAL),
+                     self.known_float(KEY_NECK_TO_EYE_VERTICAL, DEFAULT_NECK_TO_EYE_VERTICAL))
+    }
+
+    pub fn set_neck_to_eye_distance(&self, distance: Vector2<f32>) -> Result<(), Error> {
+        try!(self.set_float(KEY_NECK_TO_EYE_HORIZONTAL, distance.x));
+        self.set_float(KEY_NECK_TO_EYE_VERTICAL, distance.y)
+    }
+
+    // the profile in use
+    pub fn user(&self) -> String {
+        self.known_string(KEY_USER, "")
+    }
+
+    // the name of the user of the profile in use
+    pub fn user_name(&self) -> String {
+        self.known_string(KEY_NAME, "")
+    }
+
+    pub fn gender(&self) -> Gender {
+        Gender::from_str(&self.known_string(KEY_GENDER, DEFAULT_GENDER))
+    }
+}
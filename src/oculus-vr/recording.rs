@@ -0,0 +1,507 @@
+// Recording of sensor states to disk and replaying them later.
+//
+// A recording is a text file, the first line holds the format name and
+// version and every following line is one sample:
+//
+//     ovr-sensor-recording 1
+//     <temperature> <status flags> <recorded pose state> <predicted pose state>
+//
+// where a pose state is written as its time in seconds, orientation (w x y z),
+// position, angular velocity, linear velocity, angular acceleration and
+// linear acceleration, all separated by spaces. Samples are ordered by the
+// time of the recorded pose state.
+
+use std::error;
+use std::fmt;
+use std::fs::File;
+use std::io::{self, BufRead, BufReader, BufWriter, Write};
+use std::path::Path;
+
+use cgmath::{Quaternion, Vector3};
+
+use super::{Pose, PoseState, SensorState, SensorSource, Status};
+
+pub const FORMAT_NAME: &'static str = "ovr-sensor-recording";
+pub const FORMAT_VERSION: u32 = 1;
+
+// number of values written for each pose state
+const POSE_STATE_FIELDS: usize = 20;
+
+#[derive(Debug)]
+pub enum RecordingError {
+    Io(io::Error),
+    BadHeader,
+    UnsupportedVersion(u32),
+    BadSample(usize),
+    OutOfOrder(usize)
+}
+
+impl error::Error for RecordingError {
+    fn description(&self) -> &str {
+        match *self {
+            RecordingError::Io(ref err) => err.description(),
+            RecordingError::BadHeader => "not a sensor recording",
+            RecordingError::UnsupportedVersion(_) => "unsupported recording version",
+            RecordingError::BadSample(_) => "malformed sample",
+            RecordingError::OutOfOrder(_) => "sample is older than the one before it"
+        }
+    }
+
+    fn cause(&self) -> Option<&error::Error> {
+        match *self {
+            RecordingError::Io(ref err) => Some(err),
+            _ => None
+        }
+    }
+}
+
+impl fmt::Display for RecordingError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            RecordingError::Io(ref err) => write!(f, "{}", err),
+            RecordingError::BadHeader => write!(f, "not a sensor recording"),
+            RecordingError::UnsupportedVersion(v) =>
+                write!(f, "unsupported recording version {}, expected {}", v, FORMAT_VERSION),
+            RecordingError::BadSample(line) => write!(f, "malformed sample on line {}", line),
+            RecordingError::OutOfOrder(line) =>
+                write!(f, "sample on line {} is older than the one before it", line)
+        }
+    }
+}
+
+impl From<io::Error> for RecordingError {
+    fn from(err: io::Error) -> RecordingError {
+        RecordingError::Io(err)
+    }
+}
+
+fn write_pose_state<W: Write>(out: &mut W, ps: &PoseState) -> io::Result<()> {
+    let q = ps.pose.orientation;
+    let vectors = [ps.pose.position, ps.angular_velocity, ps.linear_velocity,
+                   ps.angular_acceleration, ps.linear_acceleration];
+
+    try!(write!(out, " {} {} {} {} {}", ps.time_in_seconds, q.s, q.v.x, q.v.y, q.v.z));
+    for v in vectors.iter() {
+        try!(write!(out, " {} {} {}", v.x, v.y, v.z));
+    }
+    Ok(())
+}
+
+fn read_pose_state(v: &[f64]) -> PoseState {
+    let vec3 = |i: usize| Vector3::new(v[i] as f32, v[i + 1] as f32, v[i + 2] as f32);
+
+    PoseState {
+        pose: Pose {
+            orientation: Quaternion::new(v[1] as f32, v[2] as f32, v[3] as f32, v[4] as f32),
+            position: vec3(5)
+        },
+        angular_velocity: vec3(8),
+        linear_velocity: vec3(11),
+        angular_acceleration: vec3(14),
+        linear_acceleration: vec3(17),
+        time_in_seconds: v[0]
+    }
+}
+
+pub struct Recorder<W: Write> {
+    out: W,
+    last_time: Option<f64>
+}
+
+impl Recorder<BufWriter<File>> {
+    pub fn create<P: AsRef<Path>>(path: P) -> io::Result<Recorder<BufWriter<File>>> {
+        let file = try!(File::create(path));
+        Recorder::new(BufWriter::new(file))
+    }
+}
+
+impl<W: Write> Recorder<W> {
+    // writes the header, samples follow with each call to record
+    pub fn new(mut out: W) -> io::Result<Recorder<W>> {
+        try!(writeln!(out, "{} {}", FORMAT_NAME, FORMAT_VERSION));
+        Ok(Recorder {
+            out: out,
+            last_time: None
+        })
+    }
+
+    // states with the same recorded time as the previous sample are
+    // skipped, this happens when polling faster than the sensor updates
+    pub fn record(&mut self, state: &SensorState) -> io::Result<()> {
+        let time = state.recorded.time_in_seconds;
+        match self.last_time {
+            Some(last) if time <= last => return Ok(()),
+            _ => ()
+        }
+        self.last_time = Some(time);
+
+        try!(write!(self.out, "{} {}", state.temperature, state.status_flags.flags));
+        try!(write_pose_state(&mut self.out, &state.recorded));
+        try!(write_pose_state(&mut self.out, &state.predicted));
+        writeln!(self.out, "")
+    }
+
+    // reads the sensor state for abs_time from the source and records it
+    pub fn sample<S: SensorSource>(&mut self, source: &S, abs_time: f64) -> io::Result<SensorState> {
+        let state = source.get_sensor_state(abs_time);
+        try!(self.record(&state));
+        Ok(state)
+    }
+
+    pub fn flush(&mut self) -> io::Result<()> {
+        self.out.flush()
+    }
+
+    pub fn into_inner(self) -> W {
+        self.out
+    }
+}
+
+// A recording loaded into memory, sensor states are looked up by time and
+// interpolated between the recorded samples
+#[derive(Clone)]
+pub struct Replay {
+    samples: Vec<SensorState>
+}
+
+impl Replay {
+    pub fn open<P: AsRef<Path>>(path: P) -> Result<Replay, RecordingError> {
+        let file = try!(File::open(path));
+        Replay::read(BufReader::new(file))
+    }
+
+    pub fn read<R: BufRead>(input: R) -> Result<Replay, RecordingError> {
+        let mut lines = input.lines();
+
+        let header = match lines.next() {
+            Some(line) => try!(line),
+            None => return Err(RecordingError::BadHeader)
+        };
+        let mut parts = header.split(' ');
+        if parts.next() != Some(FORMAT_NAME) {
+            return Err(RecordingError::BadHeader);
+        }
+        match parts.next().and_then(|v| v.parse::<u32>().ok()) {
+            Some(FORMAT_VERSION) => (),
+            Some(version) => return Err(RecordingError::UnsupportedVersion(version)),
+            None => return Err(RecordingError::BadHeader)
+        }
+
+        let mut samples: Vec<SensorState> = Vec::new();
+        for (i, line) in lines.enumerate() {
+            let line_number = i + 2;
+            let line = try!(line);
+            if line.trim().len() == 0 {
+                continue;
+            }
+
+            let values: Vec<f64> = match line.split(' ').map(|v| v.parse::<f64>()).collect() {
+                Ok(values) => values,
+                Err(_) => return Err(RecordingError::BadSample(line_number))
+            };
+            if values.len() != 2 + 2 * POSE_STATE_FIELDS {
+                return Err(RecordingError::BadSample(line_number));
+            }
+
+            // the times order and interpolate the samples, nan or infinite
+            // ones would break both
+            let times = [values[2], values[2 + POSE_STATE_FIELDS]];
+            if times.iter().any(|t| !t.is_finite()) {
+                return Err(RecordingError::BadSample(line_number));
+            }
+
+            let state = SensorState {
+                temperature: values[0] as f32,
+                status_flags: Status { flags: values[1] as u32 },
+                recorded: read_pose_state(&values[2..2 + POSE_STATE_FIELDS]),
+                predicted: read_pose_state(&values[2 + POSE_STATE_FIELDS..])
+            };
+
+            match samples.last() {
+                Some(last) if last.recorded.time_in_seconds >= state.recorded.time_in_seconds =>
+                    return Err(RecordingError::OutOfOrder(line_number)),
+                _ => ()
+            }
+            samples.push(state);
+        }
+
+        Ok(Replay { samples: samples })
+    }
+
+    // the samples are sorted by their recorded time like a file has to be,
+    // of samples with the same time only the first is kept and samples
+    // without a finite time are dropped
+    pub fn from_samples(mut samples: Vec<SensorState>) -> Replay {
+        samples.retain(|s| s.recorded.time_in_seconds.is_finite() &&
+                           s.predicted.time_in_seconds.is_finite());
+        samples.sort_by(|a, b| {
+            a.recorded.time_in_seconds.partial_cmp(&b.recorded.time_in_seconds).unwrap()
+        });
+
+        let mut ordered: Vec<SensorState> = Vec::with_capacity(samples.len());
+        for state in samples.into_iter() {
+            match ordered.last() {
+                Some(last) if last.recorded.time_in_seconds >= state.recorded.time_in_seconds => continue,
+                _ => ()
+            }
+            ordered.push(state);
+        }
+        Replay { samples: ordered }
+    }
+
+    pub fn samples(&self) -> &[SensorState] {
+        &self.samples[..]
+    }
+
+    pub fn start_time(&self) -> Option<f64> {
+        self.samples.first().map(|s| s.recorded.time_in_seconds)
+    }
+
+    pub fn end_time(&self) -> Option<f64> {
+        self.samples.last().map(|s| s.recorded.time_in_seconds)
+    }
+
+    // the sensor state at the given time, interpolated between the two
+    // closest samples. Times outside of the recording return the first or
+    // last sample and status flags are taken from the older sample.
+    pub fn sensor_state_at(&self, time: f64) -> Option<SensorState> {
+        let n = self.samples.len();
+        if n == 0 {
+            return None;
+        }
+
+        // binary search for the first sample after time
+        let (mut low, mut high) = (0, n);
+        while low < high {
+            let mid = low + (high - low) / 2;
+            if self.samples[mid].recorded.time_in_seconds > time {
+                high = mid;
+            } else {
+                low = mid + 1;
+            }
+        }
+        let next = match low {
+            0 => return Some(self.samples[0]),
+            i if i == n => return Some(self.samples[n - 1]),
+            i => i
+        };
+
+        let a = &self.samples[next - 1];
+        let b = &self.samples[next];
+        let t0 = a.recorded.time_in_seconds;
+        let t1 = b.recorded.time_in_seconds;
+        // a slerp by 0 is not always exact, the sample itself is
+        if time == t0 {
+            return Some(*a);
+        }
+        let amount = ((time - t0) / (t1 - t0)) as f32;
+
+        Some(SensorState {
+            predicted: a.predicted.lerp(&b.predicted, amount),
+            recorded: a.recorded.lerp(&b.recorded, amount),
+            temperature: a.temperature + (b.temperature - a.temperature) * amount,
+            status_flags: a.status_flags
+        })
+    }
+}
+
+impl SensorSource for Replay {
+    // an empty recording reports an untracked state at the origin
+    fn get_sensor_state(&self, abs_time: f64) -> SensorState {
+        match self.sensor_state_at(abs_time) {
+            Some(state) => state,
+            None => {
+                let zero = Vector3::new(0., 0., 0.);
+                let pose_state = PoseState {
+                    pose: Pose {
+                        orientation: Quaternion::new(1., 0., 0., 0.),
+                        position: zero
+                    },
+                    angular_velocity: zero,
+                    linear_velocity: zero,
+                    angular_acceleration: zero,
+                    linear_acceleration: zero,
+                    time_in_seconds: abs_time
+                };
+                SensorState {
+                    predicted: pose_state,
+                    recorded: pose_state,
+                    temperature: 0.,
+                    status_flags: Status { flags: 0 }
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Cursor;
+
+    use cgmath::{Quaternion, Vector3};
+
+    use super::{Recorder, RecordingError, Replay};
+    use super::super::{Pose, PoseState, SensorState, Status};
+
+    // a head turned angle radians around y, at x along the x axis
+    fn pose_state(time: f64, angle: f32, x: f32) -> PoseState {
+        let half = angle * 0.5;
+        PoseState {
+            pose: Pose {
+                orientation: Quaternion::new(half.cos(), 0., half.sin(), 0.),
+                position: Vector3::new(x, 1.5, -0.25)
+            },
+            angular_velocity: Vector3::new(0., 0.2, 0.),
+            linear_velocity: Vector3::new(0.5, 0., 0.),
+            angular_acceleration: Vector3::new(0., 0., 0.),
+            linear_acceleration: Vector3::new(0., -0.125, 0.),
+            time_in_seconds: time
+        }
+    }
+
+    fn sensor_state(time: f64, angle: f32, x: f32) -> SensorState {
+        SensorState {
+            predicted: pose_state(time + 0.03, angle + 0.01, x + 0.015),
+            recorded: pose_state(time, angle, x),
+            temperature: 31.5,
+            status_flags: Status { flags: 0x83 }
+        }
+    }
+
+    fn assert_pose_state_eq(a: &PoseState, b: &PoseState) {
+        assert_eq!(a.time_in_seconds, b.time_in_seconds);
+        assert_eq!(a.pose.orientation, b.pose.orientation);
+        assert_eq!(a.pose.position, b.pose.position);
+        assert_eq!(a.angular_velocity, b.angular_velocity);
+        assert_eq!(a.linear_velocity, b.linear_velocity);
+        assert_eq!(a.angular_acceleration, b.angular_acceleration);
+        assert_eq!(a.linear_acceleration, b.linear_acceleration);
+    }
+
+    fn assert_sensor_state_eq(a: &SensorState, b: &SensorState) {
+        assert_pose_state_eq(&a.predicted, &b.predicted);
+        assert_pose_state_eq(&a.recorded, &b.recorded);
+        assert_eq!(a.temperature, b.temperature);
+        assert_eq!(a.status_flags.flags, b.status_flags.flags);
+    }
+
+    fn record(states: &[SensorState]) -> Vec<u8> {
+        let mut recorder = Recorder::new(Vec::new()).unwrap();
+        for state in states.iter() {
+            recorder.record(state).unwrap();
+        }
+        recorder.into_inner()
+    }
+
+    fn read(text: &str) -> Result<Replay, RecordingError> {
+        Replay::read(Cursor::new(text.as_bytes().to_vec()))
+    }
+
+    #[test]
+    fn round_trip_is_exact_at_sample_times() {
+        let states = [sensor_state(10., 0., 0.), sensor_state(10.1, 0.2, 0.1),
+                      sensor_state(10.2, 0.3, 0.125)];
+        let replay = Replay::read(Cursor::new(record(&states))).unwrap();
+
+        assert_eq!(replay.samples().len(), 3);
+        for state in states.iter() {
+            let replayed = replay.sensor_state_at(state.recorded.time_in_seconds).unwrap();
+            assert_sensor_state_eq(&replayed, state);
+        }
+    }
+
+    #[test]
+    fn round_trip_interpolates_between_samples() {
+        let states = [sensor_state(10., 0., 0.), sensor_state(11., 0.2, 0.5)];
+        let replay = Replay::read(Cursor::new(record(&states))).unwrap();
+
+        let mid = replay.sensor_state_at(10.5).unwrap();
+        let expected = pose_state(10.5, 0.1, 0.25);
+        assert_eq!(mid.recorded.time_in_seconds, 10.5);
+        assert_eq!(mid.recorded.pose.position, expected.pose.position);
+        let q = mid.recorded.pose.orientation;
+        let e = expected.pose.orientation;
+        assert!((q.s - e.s).abs() < 1e-6 && (q.v.y - e.v.y).abs() < 1e-6);
+        assert_eq!(mid.temperature, 31.5);
+    }
+
+    #[test]
+    fn repeated_times_are_recorded_once() {
+        let states = [sensor_state(10., 0., 0.), sensor_state(10., 0.2, 0.1)];
+        let replay = Replay::read(Cursor::new(record(&states))).unwrap();
+        assert_eq!(replay.samples().len(), 1);
+    }
+
+    #[test]
+    fn rejects_bad_header() {
+        match read("not-a-recording 1\n") {
+            Err(RecordingError::BadHeader) => (),
+            _ => panic!("expected BadHeader")
+        }
+        match read("") {
+            Err(RecordingError::BadHeader) => (),
+            _ => panic!("expected BadHeader")
+        }
+    }
+
+    #[test]
+    fn rejects_unsupported_version() {
+        match read("ovr-sensor-recording 2\n") {
+            Err(RecordingError::UnsupportedVersion(2)) => (),
+            _ => panic!("expected UnsupportedVersion")
+        }
+    }
+
+    #[test]
+    fn rejects_out_of_order_samples() {
+        let first = record(&[sensor_state(11., 0., 0.)]);
+        let second = record(&[sensor_state(10., 0., 0.)]);
+        let mut text = String::from_utf8(first).unwrap();
+        text.push_str(String::from_utf8(second).unwrap().lines().nth(1).unwrap());
+        text.push_str("\n");
+
+        match read(&text) {
+            Err(RecordingError::OutOfOrder(3)) => (),
+            _ => panic!("expected OutOfOrder on line 3")
+        }
+    }
+
+    #[test]
+    fn rejects_times_that_are_not_finite() {
+        let text = String::from_utf8(record(&[sensor_state(10., 0., 0.)])).unwrap();
+        for bad in ["NaN", "inf", "-inf"].iter() {
+            let recorded = text.replace(" 10 ", &format!(" {} ", bad));
+            let predicted = text.replace(" 10.03 ", &format!(" {} ", bad));
+            assert!(recorded != text && predicted != text);
+            for t in [recorded, predicted].iter() {
+                match read(t) {
+                    Err(RecordingError::BadSample(2)) => (),
+                    _ => panic!("expected BadSample on line 2 for {}", bad)
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn finds_the_samples_around_a_time() {
+        let replay = Replay::from_samples((0..100).map(|i| sensor_state(i as f64, 0., i as f32))
+                                                  .collect());
+
+        assert_eq!(replay.sensor_state_at(-1.).unwrap().recorded.pose.position.x, 0.);
+        assert_eq!(replay.sensor_state_at(150.).unwrap().recorded.pose.position.x, 99.);
+        for &(time, x) in [(0., 0.), (41., 41.), (41.5, 41.5), (98.25, 98.25), (99., 99.)].iter() {
+            let state = replay.sensor_state_at(time).unwrap();
+            assert_eq!(state.recorded.time_in_seconds, time);
+            assert_eq!(state.recorded.pose.position.x, x);
+        }
+    }
+
+    #[test]
+    fn from_samples_orders_samples() {
+        let replay = Replay::from_samples(vec![sensor_state(12., 0., 0.), sensor_state(10., 0., 0.),
+                                               sensor_state(11., 0., 0.), sensor_state(10., 0.2, 0.)]);
+        let times: Vec<f64> = replay.samples().iter().map(|s| s.recorded.time_in_seconds).collect();
+        assert_eq!(times, vec![10., 11., 12.]);
+        assert_eq!(replay.samples()[0].recorded.pose.orientation, Quaternion::new(1., 0., 0., 0.));
+    }
+}
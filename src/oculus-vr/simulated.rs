@@ -0,0 +1,610 @@
+// A pure rust stand in for a real headset. It reports the same description,
+// sensor and timing types as Hmd for the DK1 and DK2 presets, with the head
+// driven by a HeadMotion. Time is a simulated clock that only moves forward
+// when the caller asks it to, which keeps runs reproducible.
+
+use cgmath::{Matrix4, Quaternion, Vector2, Vector3};
+use vr_common::{self, HeadMountedDisplay, DisplayDescription};
+
+use math::{vec3_add, vec3_sub, vec3_scale, vec3_lerp, quat_identity, quat_mul,
+           quat_conjugate, quat_slerp, quat_rotate, quat_from_euler, quat_to_euler,
+           quat_to_rotation_vector, rigid_to_mat4};
+use layout::FovTextureSize;
+use super::{ll, to_viewport, HmdType, HmdCapabilities, SensorCapabilities, DistortionCapabilities,
+            Status, Pose, PoseState, SensorState, SensorSource, SensorDescription, HmdDescription,
+            HmdDescriptionEye, EyeRenderDescriptor, FrameTiming, FovPort, Eye, PerEye, DepthRange};
+
+pub trait HeadMotion {
+    fn pose_at(&self, time: f64) -> Pose;
+}
+
+// the head never moves
+#[derive(Debug, Copy, Clone)]
+pub struct Stationary {
+    pub pose: Pose
+}
+
+impl Stationary {
+    pub fn new() -> Stationary {
+        Stationary {
+            pose: Pose {
+                orientation: quat_identity(),
+                position: Vector3::new(0., 0., 0.)
+            }
+        }
+    }
+}
+
+impl HeadMotion for Stationary {
+    fn pose_at(&self, _: f64) -> Pose {
+        self.pose
+    }
+}
+
+// procedural motion, the head looks around on independent sine waves
+// amplitudes are in radians and meters, frequencies in hz
+#[derive(Debug, Copy, Clone)]
+pub struct Sway {
+    pub yaw_amplitude: f32,
+    pub yaw_frequency: f32,
+    pub pitch_amplitude: f32,
+    pub pitch_frequency: f32,
+    pub roll_amplitude: f32,
+    pub roll_frequency: f32,
+    pub position_amplitude: Vector3<f32>,
+    pub position_frequency: f32
+}
+
+impl Sway {
+    pub fn new() -> Sway {
+        Sway {
+            yaw_amplitude: 0.6,
+            yaw_frequency: 0.1,
+            pitch_amplitude: 0.2,
+            pitch_frequency: 0.17,
+            roll_amplitude: 0.05,
+            roll_frequency: 0.23,
+            position_amplitude: Vector3::new(0.05, 0.02, 0.03),
+            position_frequency: 0.13
+        }
+    }
+}
+
+impl HeadMotion for Sway {
+    fn pose_at(&self, time: f64) -> Pose {
+        let wave = |amplitude: f32, frequency: f32, phase: f64| {
+            amplitude * ((time * frequency as f64 + phase) * 2. * ::std::f64::consts::PI).sin() as f32
+        };
+
+        Pose {
+            orientation: quat_from_euler(wave(self.yaw_amplitude, self.yaw_frequency, 0.),
+                                         wave(self.pitch_amplitude, self.pitch_frequency, 0.25),
+                                         wave(self.roll_amplitude, self.roll_frequency, 0.5)),
+            position: Vector3::new(wave(self.position_amplitude.x, self.position_frequency, 0.),
+                                   wave(self.position_amplitude.y, self.position_frequency, 0.33),
+                                   wave(self.position_amplitude.z, self.position_frequency, 0.66))
+        }
+    }
+}
+
+// scripted motion, poses are interpolated between key frames and held
+// before the first and after the last key frame
+#[derive(Debug, Clone)]
+pub struct Scripted {
+    keys: Vec<(f64, Pose)>
+}
+
+impl Scripted {
+    pub fn new() -> Scripted {
+        Scripted { keys: Vec::new() }
+    }
+
+    // key frames can be added in any order, they are kept sorted by time.
+    // A key frame at the time of an earlier one replaces it, one at a time
+    // that is not finite is ignored
+    pub fn key(mut self, time: f64, pose: Pose) -> Scripted {
+        if !time.is_finite() {
+            return self;
+        }
+        match self.keys.iter().position(|&(t, _)| t >= time) {
+            Some(i) if self.keys[i].0 == time => self.keys[i] = (time, pose),
+            Some(i) => self.keys.insert(i, (time, pose)),
+            None => self.keys.push((time, pose))
+        }
+        self
+    }
+}
+
+impl HeadMotion for Scripted {
+    fn pose_at(&self, time: f64) -> Pose {
+        if self.keys.len() == 0 {
+            return Stationary::new().pose;
+        }
+
+        let first = self.keys[0];
+        let last = self.keys[self.keys.len() - 1];
+        if time <= first.0 {
+            return first.1;
+        } else if time >= last.0 {
+            return last.1;
+        }
+
+        let next = self.keys.iter().position(|&(t, _)| t > time).unwrap();
+        let (t0, a) = self.keys[next - 1];
+        let (t1, b) = self.keys[next];
+        let t = ((time - t0) / (t1 - t0)) as f32;
+        Pose {
+            orientation: quat_slerp(a.orientation, b.orientation, t),
+            position: vec3_lerp(a.position, b.position, t)
+        }
+    }
+}
+
+// the fixed properties of a simulated headset
+#[derive(Debug, Copy, Clone)]
+pub struct Preset {
+    pub hmd_type: HmdType,
+    pub resolution: ll::Sizei,
+    pub refresh_rate: f64,
+    pub default_eye_fov: PerEye<FovPort>,
+    pub max_eye_fov: PerEye<FovPort>,
+    pub pixels_per_tan_angle_at_center: Vector2<f32>,
+    pub ipd: f32,
+    pub eye_render_order: [Eye; 2],
+    pub position_tracking: bool
+}
+
+impl Preset {
+    // the values are close to what the 0.3.2 SDK reports for the default
+    // profile, other types fall back to the DK1 preset
+    pub fn from_hmd_type(hmd_type: HmdType) -> Preset {
+        match hmd_type {
+            HmdType::DK2 | HmdType::CrystalCoveProto => Preset {
+                hmd_type: hmd_type,
+                resolution: ll::Sizei { x: 1920, y: 1080 },
+                refresh_rate: 75.,
+                default_eye_fov: PerEye::new(
+                    FovPort { up: 1.3292, down: 1.3292, left: 1.0586, right: 1.0924 },
+                    FovPort { up: 1.3292, down: 1.3292, left: 1.0924, right: 1.0586 }
+                ),
+                max_eye_fov: PerEye::new(
+                    FovPort { up: 1.5041, down: 1.5041, left: 1.2283, right: 1.1692 },
+                    FovPort { up: 1.5041, down: 1.5041, left: 1.1692, right: 1.2283 }
+                ),
+                pixels_per_tan_angle_at_center: Vector2::new(549.5, 549.5),
+                ipd: 0.064,
+                eye_render_order: [Eye::Right, Eye::Left],
+                position_tracking: true
+            },
+            _ => Preset {
+                hmd_type: HmdType::DK1,
+                resolution: ll::Sizei { x: 1280, y: 800 },
+                refresh_rate: 60.,
+                default_eye_fov: PerEye::new(
+                    FovPort { up: 1.3316, down: 1.3316, left: 1.0582, right: 0.9632 },
+                    FovPort { up: 1.3316, down: 1.3316, left: 0.9632, right: 1.0582 }
+                ),
+                max_eye_fov: PerEye::new(
+                    FovPort { up: 1.5184, down: 1.5184, left: 1.2062, right: 1.0978 },
+                    FovPort { up: 1.5184, down: 1.5184, left: 1.0978, right: 1.2062 }
+                ),
+                pixels_per_tan_angle_at_center: Vector2::new(480.7, 480.7),
+                ipd: 0.064,
+                eye_render_order: [Eye::Left, Eye::Right],
+                position_tracking: false
+            }
+        }
+    }
+}
+
+pub struct SimulatedHmd<M> {
+    preset: Preset,
+    motion: M,
+    time: f64,
+    frame_start: f64,
+    // yaw and position removed from the motion by recenter
+    origin_yaw: Quaternion<f32>,
+    origin_position: Vector3<f32>
+}
+
+// step used to derive velocities and accelerations from the motion
+const DERIVATIVE_STEP: f64 = 0.001;
+
+impl<M: HeadMotion> SimulatedHmd<M> {
+    pub fn new(hmd_type: HmdType, motion: M) -> SimulatedHmd<M> {
+        SimulatedHmd::from_preset(Preset::from_hmd_type(hmd_type), motion)
+    }
+
+    pub fn from_preset(preset: Preset, motion: M) -> SimulatedHmd<M> {
+        SimulatedHmd {
+            preset: preset,
+            motion: motion,
+            time: 0.,
+            frame_start: 0.,
+            origin_yaw: quat_identity(),
+            origin_position: Vector3::new(0., 0., 0.)
+        }
+    }
+
+    pub fn preset(&self) -> &Preset {
+        &self.preset
+    }
+
+    pub fn motion(&self) -> &M {
+        &self.motion
+    }
+
+    // the simulated equivalent of ovr::get_time
+    pub fn get_time(&self) -> f64 {
+        self.time
+    }
+
+    pub fn set_time(&mut self, time: f64) {
+        self.time = time;
+    }
+
+    pub fn advance(&mut self, seconds: f64) {
+        self.time += seconds;
+    }
+
+    pub fn get_description(&self) -> HmdDescription {
+        let p = &self.preset;
+        let mut sensor = SensorCapabilities::new()
+            .set_orientation(true)
+            .set_yaw_correction(true);
+        if p.position_tracking {
+            sensor = sensor.set_position(true);
+        }
+
+        HmdDescription {
+            hmd_type: p.hmd_type,
+            product_name: match p.hmd_type {
+                HmdType::DK1 => "Oculus Rift DK1 (simulated)",
+                _ => "Oculus Rift DK2 (simulated)"
+            }.to_string(),
+            manufacture: "Oculus VR".to_string(),
+            hmd_capabilities: HmdCapabilities {
+                flags: ll::HmdCap_Present | ll::HmdCap_Available
+            },
+            sensor_capabilities: sensor,
+            distortion_capabilities: DistortionCapabilities::new()
+                .set_chromatic(true)
+                .set_timewarp(true)
+                .set_vignette(true),
+            resolution: p.resolution,
+            window_position: ll::Vector2i { x: 0, y: 0 },
+            eye_fovs: PerEye::new(
+                HmdDescriptionEye {
+                    default_eye_fov: p.default_eye_fov.left,
+                    max_eye_fov: p.max_eye_fov.left
+                },
+                HmdDescriptionEye {
+                    default_eye_fov: p.default_eye_fov.right,
+                    max_eye_fov: p.max_eye_fov.right
+                }
+            ),
+            eye_render_order: p.eye_render_order,
+            display_device_name: "".to_string(),
+            display_id: 0
+        }
+    }
+
+    pub fn get_sensor_description(&self) -> SensorDescription {
+        SensorDescription {
+            vendor_id: 0x2833,
+            product_id: match self.preset.hmd_type {
+                HmdType::DK1 => 0x0001,
+                _ => 0x0021
+            },
+            serial_number: "SIMULATED000".to_string()
+        }
+    }
+
+    // the motion relative to the last recenter
+    fn pose_at(&self, time: f64) -> Pose {
+        let pose = self.motion.pose_at(time);
+        let inv = quat_conjugate(self.origin_yaw);
+        Pose {
+            orientation: quat_mul(inv, pose.orientation),
+            position: quat_rotate(inv, vec3_sub(pose.position, self.origin_position))
+        }
+    }
+
+    // like ovrHmd_ResetSensor, the current yaw becomes forward and the
+    // current position the origin
+    pub fn reset_sensor(&mut self) {
+        let pose = self.motion.pose_at(self.time);
+        let (yaw, _, _) = quat_to_euler(pose.orientation);
+        self.origin_yaw = quat_from_euler(yaw, 0., 0.);
+        self.origin_position = pose.position;
+    }
+
+    fn pose_state_at(&self, time: f64) -> PoseState {
+        let h = DERIVATIVE_STEP;
+        let prev = self.pose_at(time - h);
+        let pose = self.pose_at(time);
+        let next = self.pose_at(time + h);
+
+        // angular velocity is expressed in the head's frame, like the SDK's
+        // gyro readings, so that b = a * exp(w * h)
+        let angular = |a: Quaternion<f32>, b: Quaternion<f32>| {
+            vec3_scale(quat_to_rotation_vector(quat_mul(quat_conjugate(a), b)), 1. / h as f32)
+        };
+        let w0 = angular(prev.orientation, pose.orientation);
+        let w1 = angular(pose.orientation, next.orientation);
+
+        let v0 = vec3_scale(vec3_sub(pose.position, prev.position), 1. / h as f32);
+        let v1 = vec3_scale(vec3_sub(next.position, pose.position), 1. / h as f32);
+
+        PoseState {
+            pose: pose,
+            angular_velocity: vec3_scale(vec3_add(w0, w1), 0.5),
+            linear_velocity: vec3_scale(vec3_add(v0, v1), 0.5),
+            angular_acceleration: vec3_scale(vec3_sub(w1, w0), 1. / h as f32),
+            linear_acceleration: vec3_scale(vec3_sub(v1, v0), 1. / h as f32),
+            time_in_seconds: time
+        }
+    }
+
+    // recorded is sampled at the current simulated time, predicted at abs_time
+    pub fn get_sensor_state(&self, abs_time: f64) -> SensorState {
+        let mut flags = ll::Status_OrientationTracked | ll::Status_HmdConnected;
+        if self.preset.position_tracking {
+            flags |= ll::Status_PositionTracked | ll::Status_PositionConnected;
+        }
+
+        SensorState {
+            predicted: self.pose_state_at(abs_time),
+            recorded: self.pose_state_at(self.time),
+            temperature: 35.,
+            status_flags: Status { flags: flags }
+        }
+    }
+
+    pub fn get_fov_texture_size(&self,
+                                _: Eye,
+                                fov: FovPort,
+                                pixels_per_display_pixel: f32) -> ll::Sizei {
+        let ppt = self.preset.pixels_per_tan_angle_at_center;
+        ll::Sizei {
+            x: ((fov.left + fov.right) * ppt.x * pixels_per_display_pixel).ceil() as i32,
+            y: ((fov.up + fov.down) * ppt.y * pixels_per_display_pixel).ceil() as i32
+        }
+    }
+
+    pub fn get_render_description(&self, eye: Eye, fov: FovPort) -> EyeRenderDescriptor {
+        let p = &self.preset;
+        let half = p.resolution.x / 2;
+        let (x, adjust) = match eye {
+            Eye::Left => (0, p.ipd * 0.5),
+            Eye::Right => (half, -p.ipd * 0.5)
+        };
+
+        EyeRenderDescriptor {
+            eye: eye,
+            fov: fov,
+            distorted_viewport: ll::Recti {
+                pos: ll::Vector2i { x: x, y: 0 },
+                size: ll::Sizei { x: half, y: p.resolution.y }
+            },
+            pixels_per_tan_angle_at_center: p.pixels_per_tan_angle_at_center,
+            view_adjust: Vector3::new(adjust, 0., 0.)
+        }
+    }
+
+    // timing for a frame, frames are spaced one refresh interval apart
+    // starting at the simulated time of the last reset
+    pub fn get_frame_timing(&self, frame_index: usize) -> FrameTiming {
+        let interval = 1. / self.preset.refresh_rate;
+        let this_frame = self.frame_start + frame_index as f64 * interval;
+        let next_frame = this_frame + interval;
+        let (first, second) = (next_frame + interval * 0.25, next_frame + interval * 0.75);
+
+        FrameTiming {
+            delta_seconds: interval as f32,
+            this_frame_seconds: this_frame,
+            timewarp_point_seconds: next_frame - interval * 0.2,
+            next_frame_seconds: next_frame,
+            scanout_midpoint_seconds: next_frame + interval * 0.5,
+            eye_scanout_seconds: match self.preset.eye_render_order[0] {
+                Eye::Left => PerEye::new(first, second),
+                Eye::Right => PerEye::new(second, first)
+            }
+        }
+    }
+
+    // moves the simulated clock to the start of the frame
+    pub fn begin_frame_timing(&mut self, frame_index: usize) -> FrameTiming {
+        let timing = self.get_frame_timing(frame_index);
+        if timing.this_frame_seconds > self.time {
+            self.time = timing.this_frame_seconds;
+        }
+        timing
+    }
+
+    pub fn end_frame_timing(&mut self) {}
+
+    pub fn reset_frame_timing(&mut self, frame_index: usize) {
+        let interval = 1. / self.preset.refresh_rate;
+        self.frame_start = self.time - frame_index as f64 * interval;
+    }
+
+    pub fn get_eye_pose(&self, eye: Eye) -> Pose {
+        let timing = self.get_frame_timing(self.frame_index());
+        self.pose_at(*timing.eye_scanout_seconds.eye(eye))
+    }
+
+    fn frame_index(&self) -> usize {
+        let interval = 1. / self.preset.refresh_rate;
+        let index = ((self.time - self.frame_start) / interval + 1e-6).floor();
+        if index < 0. { 0 } else { index as usize }
+    }
+}
+
+impl<M: HeadMotion> FovTextureSize for SimulatedHmd<M> {
+    fn get_fov_texture_size(&self, eye: Eye, fov: FovPort, pixels_per_display_pixel: f32) -> ll::Sizei {
+        SimulatedHmd::get_fov_texture_size(self, eye, fov, pixels_per_display_pixel)
+    }
+}
+
+impl<M: HeadMotion> SensorSource for SimulatedHmd<M> {
+    fn get_sensor_state(&self, abs_time: f64) -> SensorState {
+        SimulatedHmd::get_sensor_state(self, abs_time)
+    }
+}
+
+impl<M: HeadMotion> HeadMountedDisplay for SimulatedHmd<M> {
+    fn description(&self) -> DisplayDescription {
+        let desc = self.get_description();
+        DisplayDescription {
+            name: desc.product_name,
+            resolution: (desc.resolution.x as u32, desc.resolution.y as u32),
+            window_position: (desc.window_position.x, desc.window_position.y)
+        }
+    }
+
+    fn recommended_render_size(&self, eye: vr_common::Eye) -> (u32, u32) {
+        let eye = Eye::from_common(eye);
+        let size = self.get_fov_texture_size(eye, *self.preset.default_eye_fov.eye(eye), 1.);
+        (size.x as u32, size.y as u32)
+    }
+
+    fn fov(&self, eye: vr_common::Eye) -> vr_common::Fov {
+        self.preset.default_eye_fov.eye(Eye::from_common(eye)).to_common()
+    }
+
+    fn projection(&self, eye: vr_common::Eye, znear: f32, zfar: f32) -> Matrix4<f32> {
+        let fov = self.preset.default_eye_fov.eye(Eye::from_common(eye));
+        fov.projection_builder(znear, zfar).depth_range(DepthRange::MinusOneToOne).build()
+    }
+
+    fn eye_viewport(&self, eye: vr_common::Eye) -> vr_common::Viewport {
+        let eye = Eye::from_common(eye);
+        let fov = *self.preset.default_eye_fov.eye(eye);
+        to_viewport(self.get_render_description(eye, fov).distorted_viewport)
+    }
+
+    fn head_pose(&self, seconds_from_now: f32) -> Option<Matrix4<f32>> {
+        let pose = self.pose_at(self.time + seconds_from_now as f64);
+        Some(rigid_to_mat4(pose.orientation, pose.position))
+    }
+
+    fn recenter(&mut self) {
+        self.reset_sensor();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::f64;
+
+    use cgmath::Vector3;
+
+    use math::{quat_from_euler, quat_to_euler};
+    use super::{HeadMotion, Scripted, SimulatedHmd, Stationary};
+    use super::super::{Eye, HmdType, Pose};
+
+    fn at(x: f32, yaw: f32) -> Pose {
+        Pose {
+            orientation: quat_from_euler(yaw, 0., 0.),
+            position: Vector3::new(x, 0., 0.)
+        }
+    }
+
+    fn assert_close(a: f32, b: f32) {
+        assert!((a - b).abs() < 1e-3, "{} != {}", a, b);
+    }
+
+    #[test]
+    fn scripted_keys_are_sorted() {
+        let motion = Scripted::new()
+            .key(2., at(4., 0.))
+            .key(0., at(0., 0.))
+            .key(1., at(2., 0.));
+
+        assert_close(motion.pose_at(0.5).position.x, 1.);
+        assert_close(motion.pose_at(1.5).position.x, 3.);
+    }
+
+    #[test]
+    fn scripted_holds_outside_its_keys() {
+        let motion = Scripted::new().key(1., at(1., 0.)).key(2., at(2., 0.));
+        assert_eq!(motion.pose_at(0.).position.x, 1.);
+        assert_eq!(motion.pose_at(3.).position.x, 2.);
+    }
+
+    #[test]
+    fn scripted_replaces_keys_at_the_same_time_and_ignores_bad_times() {
+        let motion = Scripted::new()
+            .key(0., at(0., 0.))
+            .key(1., at(1., 0.))
+            .key(1., at(5., 0.))
+            .key(f64::NAN, at(9., 0.))
+            .key(f64::INFINITY, at(9., 0.));
+        assert_eq!(motion.pose_at(1.).position.x, 5.);
+        assert_eq!(motion.pose_at(10.).position.x, 5.);
+    }
+
+    #[test]
+    fn velocities_follow_the_motion() {
+        // 2 m/s along x and 1 rad/s of yaw
+        let motion = Scripted::new().key(0., at(0., 0.)).key(1., at(2., 1.));
+        let mut hmd = SimulatedHmd::new(HmdType::DK2, motion);
+        hmd.set_time(0.5);
+
+        let state = hmd.get_sensor_state(0.75);
+        let recorded = state.recorded;
+        assert_eq!(recorded.time_in_seconds, 0.5);
+        assert_close(recorded.pose.position.x, 1.);
+        assert_close(recorded.linear_velocity.x, 2.);
+        assert_close(recorded.angular_velocity.y, 1.);
+        assert_close(recorded.angular_velocity.x, 0.);
+        assert_close(state.predicted.pose.position.x, 1.5);
+        assert!(state.status_flags.position_tracked());
+    }
+
+    #[test]
+    fn reset_sensor_recenters_yaw_and_position() {
+        let motion = Stationary {
+            pose: Pose {
+                orientation: quat_from_euler(0.5, 0.2, 0.),
+                position: Vector3::new(1., 2., 3.)
+            }
+        };
+        let mut hmd = SimulatedHmd::new(HmdType::DK1, motion);
+        hmd.reset_sensor();
+
+        let pose = hmd.get_sensor_state(0.).recorded.pose;
+        let (yaw, pitch, roll) = quat_to_euler(pose.orientation);
+        assert_close(yaw, 0.);
+        assert_close(pitch, 0.2);
+        assert_close(roll, 0.);
+        assert_close(pose.position.x, 0.);
+        assert_close(pose.position.y, 0.);
+        assert_close(pose.position.z, 0.);
+        assert!(!hmd.get_sensor_state(0.).status_flags.position_tracked());
+    }
+
+    #[test]
+    fn frames_are_one_refresh_apart() {
+        let mut hmd = SimulatedHmd::new(HmdType::DK2, Stationary::new());
+        let first = hmd.begin_frame_timing(0);
+        let second = hmd.begin_frame_timing(1);
+
+        assert_eq!(hmd.get_time(), second.this_frame_seconds);
+        assert!((second.this_frame_seconds - first.this_frame_seconds - 1. / 75.).abs() < 1e-9);
+        // the DK2 scans out the right eye first
+        assert!(second.eye_scanout_seconds.right < second.eye_scanout_seconds.left);
+        assert_eq!(*second.eye_scanout_seconds.eye(Eye::Right), second.eye_scanout_seconds.right);
+    }
+
+    #[test]
+    fn presets_describe_the_headsets() {
+        let dk1 = SimulatedHmd::new(HmdType::DK1, Stationary::new()).get_description();
+        let dk2 = SimulatedHmd::new(HmdType::DK2, Stationary::new()).get_description();
+        assert_eq!((dk1.resolution.x, dk1.resolution.y), (1280, 800));
+        assert_eq!((dk2.resolution.x, dk2.resolution.y), (1920, 1080));
+        assert!(!dk1.sensor_capabilities.position());
+        assert!(dk2.sensor_capabilities.position());
+    }
+}
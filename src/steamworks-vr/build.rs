@@ -0,0 +1,37 @@
+use std::env;
+use std::path::Path;
+use std::process::Command;
+
+// builds the C wrapper around the SteamVR IHmd interface into OUT_DIR, the
+// SteamVR SDK is expected in thirdparty/steamworks-vr-api at the root of the
+// repository
+fn main() {
+    let out_dir = match env::var("OUT_DIR") {
+        Ok(dir) => dir,
+        Err(_) => panic!("OUT_DIR is not set")
+    };
+    let manifest_dir = match env::var("CARGO_MANIFEST_DIR") {
+        Ok(dir) => dir,
+        Err(_) => panic!("CARGO_MANIFEST_DIR is not set")
+    };
+
+    let status = match Command::new("make")
+            .arg("-C").arg(&manifest_dir)
+            .arg(format!("OUT_DIR={}", out_dir))
+            .status() {
+        Ok(status) => status,
+        Err(err) => panic!("Failed to run make: {}", err)
+    };
+    if !status.success() {
+        panic!("Failed to build");
+    }
+
+    let steam_lib = Path::new(&manifest_dir)
+            .join("../../thirdparty/steamworks-vr-api/redistributable_bin/linux64");
+    println!("cargo:rustc-link-search=native={}", out_dir);
+    println!("cargo:rustc-link-search=native={}", steam_lib.display());
+
+    println!("cargo:rerun-if-changed=build.rs");
+    println!("cargo:rerun-if-changed=Makefile");
+    println!("cargo:rerun-if-changed=wrapper.cpp");
+}
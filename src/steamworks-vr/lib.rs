@@ -1,131 +1,119 @@
-#![allow(non_camel_case_types)]
-
-#![crate_name = "steamworks-vr#0.1"]
+#![crate_name = "steamworks_vr"]
 #![crate_type = "lib"]
-#![feature(link_args)]
-
+#![allow(non_camel_case_types, non_upper_case_globals, non_snake_case)]
 
 extern crate cgmath;
-use std::libc::{c_float};
+extern crate libc;
+extern crate vr_common;
+
+use libc::{c_float, c_char};
+use std::error;
+use std::fmt;
+
+use cgmath::Matrix4;
+
+use vr_common::{HeadMountedDisplay, DisplayDescription, Fov, Viewport};
 
 #[link(name="steam_api")]
 #[link(name="steamvr_wrapper")]
-extern {}
+extern "C" {}
 
 pub mod ll {
-    use std::libc::{c_float, c_char};
+    use libc::{c_float, c_char, c_int};
 
+    #[derive(Clone, Debug, Copy)]
     pub enum IHmd {}
 
+    pub type HmdEye = c_int;
+    pub const Eye_Left                              : HmdEye = 0;
+    pub const Eye_Right                             : HmdEye = 1;
+
+    pub type HmdTrackingResult = c_int;
+    pub const TrackingResult_Uninitialized          : HmdTrackingResult = 1;
+    pub const TrackingResult_Calibrating_InProgress : HmdTrackingResult = 100;
+    pub const TrackingResult_Calibrating_OutOfRange : HmdTrackingResult = 101;
+    pub const TrackingResult_Running_OK             : HmdTrackingResult = 200;
+    pub const TrackingResult_Running_OutOfRange     : HmdTrackingResult = 201;
+
+    pub type HmdError = c_int;
+    pub const HmdError_None                         : HmdError = 0;
+
+    pub const HmdError_Init_InstallationNotFound    : HmdError = 100;
+    pub const HmdError_Init_InstallationCorrupt     : HmdError = 101;
+    pub const HmdError_Init_VRClientDLLNotFound     : HmdError = 102;
+    pub const HmdError_Init_FileNotFound            : HmdError = 103;
+    pub const HmdError_Init_FactoryNotFound         : HmdError = 104;
+    pub const HmdError_Init_InterfaceNotFound       : HmdError = 105;
+    pub const HmdError_Init_InvalidInterface        : HmdError = 106;
+    pub const HmdError_Init_UserConfigDirectoryInvalid : HmdError = 107;
+    pub const HmdError_Init_HmdNotFound             : HmdError = 108;
+    pub const HmdError_Init_NotInitialized          : HmdError = 109;
+
+    pub const HmdError_Driver_Failed                : HmdError = 200;
+
+    pub const HmdError_IPC_ServerInitFailed         : HmdError = 300;
+    pub const HmdError_IPC_ConnectFailed            : HmdError = 301;
+    pub const HmdError_IPC_SharedStateInitFailed    : HmdError = 302;
+
+    pub type GraphicsAPIConvention = c_int;
+    pub const API_DirectX                           : GraphicsAPIConvention = 0;
+    pub const API_OpenGL                            : GraphicsAPIConvention = 1;
+
+    // both matrix types are row major, the translation is in the last column
+    #[derive(Clone, Default, Debug, Copy)]
     #[repr(C)]
-    pub enum HmdEye {
-        Eye_Left = 0,
-        Eye_Right = 1
-    }
-
-    #[repr(C)]
-    pub enum HmdTrackingResult {
-        TrackingResult_Uninitialized            = 1,
-        TrackingResult_Calibrating_InProgress   = 100,
-        TrackingResult_Calibrating_OutOfRange   = 101,
-        TrackingResult_Running_OK               = 200,
-        TrackingResult_Running_OutOfRange       = 201,
-    }
-
-    #[repr(C)]
-    pub enum HmdError {
-        HmdError_None = 0,
-
-        HmdError_Init_InstallationNotFound  = 100,
-        HmdError_Init_InstallationCorrupt   = 101,
-        HmdError_Init_VRClientDLLNotFound   = 102,
-        HmdError_Init_FileNotFound          = 103,
-        HmdError_Init_FactoryNotFound       = 104,
-        HmdError_Init_InterfaceNotFound     = 105,
-        HmdError_Init_InvalidInterface      = 106,
-        HmdError_Init_UserConfigDirectoryInvalid = 107,
-        HmdError_Init_HmdNotFound           = 108,
-        HmdError_Init_NotInitialized        = 109,
-
-        HmdError_Driver_Failed = 200,
-
-        HmdError_IPC_ServerInitFailed       = 300,
-        HmdError_IPC_ConnectFailed          = 301,
-        HmdError_IPC_SharedStateInitFailed  = 302,
-    }
-
-    #[repr(C)]
-    pub enum GraphicsAPIConvention {
-        API_DirectX = 0,
-        API_OpenGL = 1
-    }
-
     pub struct HmdMatrix44_t {
-        m: [[c_float, ..4], ..4]
-    }
-
-    impl HmdMatrix44_t {
-        pub fn zero() -> HmdMatrix44_t
-        {
-            HmdMatrix44_t {
-                m:  [[0., 0., 0., 0.],
-                     [0., 0., 0., 0.],
-                     [0., 0., 0., 0.],
-                     [0., 0., 0., 0.]]
-            }
-        }
+        pub m: [[c_float; 4]; 4]
     }
 
+    #[derive(Clone, Default, Debug, Copy)]
+    #[repr(C)]
     pub struct HmdMatrix34_t {
-        m: [[c_float, ..4], ..3]
-    }
-
-    impl HmdMatrix34_t {
-        pub fn zero() -> HmdMatrix34_t
-        {
-            HmdMatrix34_t {
-                m:  [[0., 0., 0., 0.],
-                     [0., 0., 0., 0.],
-                     [0., 0., 0., 0.]]
-            }
-        }
+        pub m: [[c_float; 4]; 3]
     }
 
+    #[derive(Clone, Default, Debug, Copy)]
+    #[repr(C)]
     pub struct DistortionCoordinates_t {
-        rfRed: [c_float, ..2],
-        rfGreen: [c_float, ..2],
-        rfBlue: [c_float, ..2]
+        pub rfRed: [c_float; 2],
+        pub rfGreen: [c_float; 2],
+        pub rfBlue: [c_float; 2]
     }
 
     extern "C" {
-        pub fn VR_Init(peError: *mut HmdError) -> *IHmd;
+        pub fn VR_Init(peError: *mut HmdError) -> *mut IHmd;
         pub fn VR_Shutdown();
-        pub fn VR_IHmd_Version() -> *c_char;
-
-        pub fn VR_IHmd_GetWindowBounds(ihmd: *IHmd, pnX: *mut i32, pnY: *mut i32, pnWidth: *mut u32, pnHeight: *mut u32);
-        pub fn VR_IHmd_GetRecommendedRenderTargetSize(ihmd: *IHmd, pnWidth: *mut u32, pnHeight: *mut u32);
-        pub fn VR_IHmd_GetEyeOutputViewport(ihmd: *IHmd, eye: HmdEye, pnX: *mut u32, pnY: *mut u32, pnWidth: *mut u32, pnHeight: *mut u32);
-        pub fn VR_IHmd_GetProjectionMatrix(ihmd: *IHmd, eye: HmdEye, fNearZ: c_float, fFarZ: c_float, eProjType: GraphicsAPIConvention) -> HmdMatrix44_t;
-        pub fn VR_IHmd_GetProjectionRaw(ihmd: *IHmd, eye: HmdEye, pfLeft: *mut c_float, pfRight: *mut c_float, pfTop: *mut c_float, pfBottom: *mut c_float);
-        pub fn VR_IHmd_ComputeDistortion(ihmd: *IHmd, eye: HmdEye, fU: c_float, fV: c_float) -> DistortionCoordinates_t;
-        pub fn VR_IHmd_GetEyeMatrix(ihmd: *IHmd, eye: HmdEye) -> HmdMatrix44_t;
-        pub fn VR_IHmd_GetViewMatrix(ihmd: *IHmd, fSecondsFromNow: c_float, pMatLeftView: *mut HmdMatrix44_t, pMatRightView: *mut HmdMatrix44_t, peResult: *mut HmdTrackingResult) -> bool;
-        pub fn VR_IHmd_GetWorldFromHeadPose(ihmd: *IHmd, fPredictedSecondsFromNow: c_float, pmPose: *mut HmdMatrix34_t, peResult: *mut HmdTrackingResult) -> bool;
-        pub fn VR_IHmd_GetLastWorldFromHeadPose(ihmd: *IHmd, pmPose: *HmdMatrix34_t) -> bool;
-        pub fn VR_IHmd_WillDriftInYaw(ihmd: *IHmd) -> bool;
-        pub fn VR_IHmd_ZeroTracker(ihmd: *IHmd);
-        pub fn VR_IHmd_GetDriverId(ihmd: *IHmd, pchBuffer: *mut c_char, unBufferLen: u32) -> u32;
-        pub fn VR_IHmd_GetDisplayId(ihmd: *IHmd, pchBuffer: *mut c_char, unBufferLen: u32) -> u32;
+        pub fn VR_IHmd_Version() -> *const c_char;
+
+        pub fn VR_IHmd_GetWindowBounds(ihmd: *mut IHmd, pnX: *mut i32, pnY: *mut i32, pnWidth: *mut u32, pnHeight: *mut u32);
+        pub fn VR_IHmd_GetRecommendedRenderTargetSize(ihmd: *mut IHmd, pnWidth: *mut u32, pnHeight: *mut u32);
+        pub fn VR_IHmd_GetEyeOutputViewport(ihmd: *mut IHmd, eye: HmdEye, pnX: *mut u32, pnY: *mut u32, pnWidth: *mut u32, pnHeight: *mut u32);
+        pub fn VR_IHmd_GetProjectionMatrix(ihmd: *mut IHmd, eye: HmdEye, fNearZ: c_float, fFarZ: c_float, eProjType: GraphicsAPIConvention) -> HmdMatrix44_t;
+        pub fn VR_IHmd_GetProjectionRaw(ihmd: *mut IHmd, eye: HmdEye, pfLeft: *mut c_float, pfRight: *mut c_float, pfTop: *mut c_float, pfBottom: *mut c_float);
+        pub fn VR_IHmd_ComputeDistortion(ihmd: *mut IHmd, eye: HmdEye, fU: c_float, fV: c_float) -> DistortionCoordinates_t;
+        pub fn VR_IHmd_GetEyeMatrix(ihmd: *mut IHmd, eye: HmdEye) -> HmdMatrix44_t;
+        pub fn VR_IHmd_GetViewMatrix(ihmd: *mut IHmd, fSecondsFromNow: c_float, pMatLeftView: *mut HmdMatrix44_t, pMatRightView: *mut HmdMatrix44_t, peResult: *mut HmdTrackingResult) -> bool;
+        pub fn VR_IHmd_GetWorldFromHeadPose(ihmd: *mut IHmd, fPredictedSecondsFromNow: c_float, pmPose: *mut HmdMatrix34_t, peResult: *mut HmdTrackingResult) -> bool;
+        pub fn VR_IHmd_GetLastWorldFromHeadPose(ihmd: *mut IHmd, pmPose: *mut HmdMatrix34_t) -> bool;
+        pub fn VR_IHmd_WillDriftInYaw(ihmd: *mut IHmd) -> bool;
+        pub fn VR_IHmd_ZeroTracker(ihmd: *mut IHmd);
+        pub fn VR_IHmd_GetDriverId(ihmd: *mut IHmd, pchBuffer: *mut c_char, unBufferLen: u32) -> u32;
+        pub fn VR_IHmd_GetDisplayId(ihmd: *mut IHmd, pchBuffer: *mut c_char, unBufferLen: u32) -> u32;
     }
 }
 
-pub struct Hmd
-{
-    ptr: *ll::IHmd
+pub struct Hmd {
+    ptr: *mut ll::IHmd
+}
+
+impl Drop for Hmd {
+    fn drop(&mut self) {
+        unsafe { ll::VR_Shutdown(); }
+    }
 }
 
-pub enum HmdErrorInit
-{
+#[derive(Debug, Copy, Clone)]
+pub enum HmdErrorInit {
     InstallationNotFound,
     InstallationCorrupt,
     VRClientDLLNotFound,
@@ -135,171 +123,206 @@ pub enum HmdErrorInit
     InvalidInterface,
     UserConfigDirectoryInvalid,
     HmdNotFound,
-    NotInitialized,    
+    NotInitialized,
 }
 
-pub enum IPCError
-{
+#[derive(Debug, Copy, Clone)]
+pub enum IPCError {
     ServerInitFailed,
     ConnectFailed,
-    SharedStateInitFailed,    
+    SharedStateInitFailed,
 }
 
+#[derive(Debug, Copy, Clone)]
 pub enum HmdError {
     Init(HmdErrorInit),
-    Driver_Failed,
+    DriverFailed,
     IPC(IPCError),
-    Unknown
+    Unknown(i32)
 }
 
-impl HmdError
-{
-    pub fn new_from_code(err: ll::HmdError) -> HmdError
-    {
+impl HmdError {
+    pub fn new_from_code(err: ll::HmdError) -> HmdError {
         match err {
-            ll::HmdError_Init_InstallationNotFound => Init(InstallationNotFound),
-            ll::HmdError_Init_InstallationCorrupt  => Init(InstallationCorrupt),
-            ll::HmdError_Init_VRClientDLLNotFound  => Init(VRClientDLLNotFound),
-            ll::HmdError_Init_FileNotFound         => Init(FileNotFound),
-            ll::HmdError_Init_FactoryNotFound      => Init(FactoryNotFound),
-            ll::HmdError_Init_InterfaceNotFound    => Init(InterfaceNotFound),
-            ll::HmdError_Init_InvalidInterface     => Init(InvalidInterface),
-            ll::HmdError_Init_UserConfigDirectoryInvalid  => Init(UserConfigDirectoryInvalid),
-            ll::HmdError_Init_HmdNotFound          => Init(HmdNotFound),
-            ll::HmdError_Init_NotInitialized       => Init(NotInitialized),
-
-            ll::HmdError_Driver_Failed             => Driver_Failed,
-
-            ll::HmdError_IPC_ServerInitFailed       => IPC(ServerInitFailed),
-            ll::HmdError_IPC_ConnectFailed          => IPC(ConnectFailed),
-            ll::HmdError_IPC_SharedStateInitFailed  => IPC(SharedStateInitFailed),
-
-            _ => Unknown
+            ll::HmdError_Init_InstallationNotFound => HmdError::Init(HmdErrorInit::InstallationNotFound),
+            ll::HmdError_Init_InstallationCorrupt  => HmdError::Init(HmdErrorInit::InstallationCorrupt),
+            ll::HmdError_Init_VRClientDLLNotFound  => HmdError::Init(HmdErrorInit::VRClientDLLNotFound),
+            ll::HmdError_Init_FileNotFound         => HmdError::Init(HmdErrorInit::FileNotFound),
+            ll::HmdError_Init_FactoryNotFound      => HmdError::Init(HmdErrorInit::FactoryNotFound),
+            ll::HmdError_Init_InterfaceNotFound    => HmdError::Init(HmdErrorInit::InterfaceNotFound),
+            ll::HmdError_Init_InvalidInterface     => HmdError::Init(HmdErrorInit::InvalidInterface),
+            ll::HmdError_Init_UserConfigDirectoryInvalid => HmdError::Init(HmdErrorInit::UserConfigDirectoryInvalid),
+            ll::HmdError_Init_HmdNotFound          => HmdError::Init(HmdErrorInit::HmdNotFound),
+            ll::HmdError_Init_NotInitialized       => HmdError::Init(HmdErrorInit::NotInitialized),
+
+            ll::HmdError_Driver_Failed             => HmdError::DriverFailed,
+
+            ll::HmdError_IPC_ServerInitFailed      => HmdError::IPC(IPCError::ServerInitFailed),
+            ll::HmdError_IPC_ConnectFailed         => HmdError::IPC(IPCError::ConnectFailed),
+            ll::HmdError_IPC_SharedStateInitFailed => HmdError::IPC(IPCError::SharedStateInitFailed),
+
+            code => HmdError::Unknown(code)
+        }
+    }
+
+    fn message(&self) -> &'static str {
+        match *self {
+            HmdError::Init(HmdErrorInit::InstallationNotFound) => "SteamVR installation not found",
+            HmdError::Init(HmdErrorInit::InstallationCorrupt) => "SteamVR installation is corrupt",
+            HmdError::Init(HmdErrorInit::VRClientDLLNotFound) => "vrclient library not found",
+            HmdError::Init(HmdErrorInit::FileNotFound) => "file not found",
+            HmdError::Init(HmdErrorInit::FactoryNotFound) => "factory not found",
+            HmdError::Init(HmdErrorInit::InterfaceNotFound) => "interface not found",
+            HmdError::Init(HmdErrorInit::InvalidInterface) => "invalid interface",
+            HmdError::Init(HmdErrorInit::UserConfigDirectoryInvalid) => "user config directory is invalid",
+            HmdError::Init(HmdErrorInit::HmdNotFound) => "hmd not found",
+            HmdError::Init(HmdErrorInit::NotInitialized) => "not initialized",
+            HmdError::DriverFailed => "driver failed",
+            HmdError::IPC(IPCError::ServerInitFailed) => "IPC server init failed",
+            HmdError::IPC(IPCError::ConnectFailed) => "IPC connect failed",
+            HmdError::IPC(IPCError::SharedStateInitFailed) => "IPC shared state init failed",
+            HmdError::Unknown(_) => "unknown error code"
         }
     }
 }
 
-pub enum HmdEye
-{
-    EyeLeft,
-    EyeRight
+impl error::Error for HmdError {
+    fn description(&self) -> &str {
+        self.message()
+    }
 }
 
-impl HmdEye {
-    fn to_ll(&self) -> ll::HmdEye
-    {
+impl fmt::Display for HmdError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         match *self {
-            EyeLeft => ll::Eye_Left,
-            EyeRight => ll::Eye_Right
+            HmdError::Unknown(code) => write!(f, "{} {}", self.message(), code),
+            _ => f.write_str(self.message())
+        }
+    }
+}
+
+#[derive(Debug, Copy, Clone)]
+pub enum HmdEye {
+    Left,
+    Right
+}
+
+impl HmdEye {
+    fn to_ll(self) -> ll::HmdEye {
+        match self {
+            HmdEye::Left => ll::Eye_Left,
+            HmdEye::Right => ll::Eye_Right
+        }
+    }
+
+    fn from_common(eye: vr_common::Eye) -> HmdEye {
+        match eye {
+            vr_common::Eye::Left => HmdEye::Left,
+            vr_common::Eye::Right => HmdEye::Right
         }
     }
 }
 
-pub enum GraphicsAPIConvention
-{
+#[derive(Debug, Copy, Clone)]
+pub enum GraphicsAPIConvention {
     DirectX,
     OpenGL
 }
 
-impl GraphicsAPIConvention
-{
-    fn to_ll(&self) -> ll::GraphicsAPIConvention
-    {
-        match *self {
-            DirectX => ll::API_DirectX,
-            OpenGL => ll::API_OpenGL
+impl GraphicsAPIConvention {
+    fn to_ll(self) -> ll::GraphicsAPIConvention {
+        match self {
+            GraphicsAPIConvention::DirectX => ll::API_DirectX,
+            GraphicsAPIConvention::OpenGL => ll::API_OpenGL
         }
     }
 }
 
-pub struct DistortionCoordinates
-{
-    red: [f32, ..2],
-    green: [f32, ..2],
-    blue: [f32, ..2]    
+#[derive(Debug, Copy, Clone)]
+pub struct DistortionCoordinates {
+    pub red: [f32; 2],
+    pub green: [f32; 2],
+    pub blue: [f32; 2]
 }
 
-pub enum HmdTrackingResultCalibrating
-{
-    Calibrating_InProgress,
-    Calibrating_OutOfRange
+#[derive(Debug, Copy, Clone)]
+pub enum HmdTrackingResultCalibrating {
+    InProgress,
+    OutOfRange
 }
 
-pub enum HmdTrackingResultRunning
-{
-    Running_OK,
-    Running_OutOfRange
+#[derive(Debug, Copy, Clone)]
+pub enum HmdTrackingResultRunning {
+    Ok,
+    OutOfRange
 }
 
-pub enum HmdTrackingResult
-{
+#[derive(Debug, Copy, Clone)]
+pub enum HmdTrackingResult {
     Uninitialized,
     Calibration(HmdTrackingResultCalibrating),
     Running(HmdTrackingResultRunning)
 }
 
-impl HmdTrackingResult
-{
-    fn from_ll(tr: ll::HmdTrackingResult) -> HmdTrackingResult
-    {
+impl HmdTrackingResult {
+    fn from_ll(tr: ll::HmdTrackingResult) -> HmdTrackingResult {
         match tr {
-            ll::TrackingResult_Uninitialized            => Uninitialized,
-            ll::TrackingResult_Calibrating_InProgress   => Calibration(Calibrating_InProgress),
-            ll::TrackingResult_Calibrating_OutOfRange   => Calibration(Calibrating_OutOfRange),
-            ll::TrackingResult_Running_OK               => Running(Running_OK),
-            ll::TrackingResult_Running_OutOfRange       => Running(Running_OutOfRange)
+            ll::TrackingResult_Calibrating_InProgress =>
+                HmdTrackingResult::Calibration(HmdTrackingResultCalibrating::InProgress),
+            ll::TrackingResult_Calibrating_OutOfRange =>
+                HmdTrackingResult::Calibration(HmdTrackingResultCalibrating::OutOfRange),
+            ll::TrackingResult_Running_OK =>
+                HmdTrackingResult::Running(HmdTrackingResultRunning::Ok),
+            ll::TrackingResult_Running_OutOfRange =>
+                HmdTrackingResult::Running(HmdTrackingResultRunning::OutOfRange),
+            _ => HmdTrackingResult::Uninitialized
         }
     }
 }
 
-fn to_mat4(mat: &ll::HmdMatrix44_t) -> cgmath::matrix::Mat4<f32>
-{
-    cgmath::matrix::Mat4::new(
-        mat.m[0][0], mat.m[0][1], mat.m[0][2], mat.m[0][3],
-        mat.m[1][0], mat.m[1][1], mat.m[1][2], mat.m[1][3],
-        mat.m[2][0], mat.m[2][1], mat.m[2][2], mat.m[2][3],
-        mat.m[3][0], mat.m[3][1], mat.m[3][2], mat.m[3][3],
+// cgmath is column major, so each column of the result is read down a
+// column of the row major steam matrix
+fn to_mat4(mat: &ll::HmdMatrix44_t) -> Matrix4<f32> {
+    let m = &mat.m;
+    Matrix4::new(
+        m[0][0], m[1][0], m[2][0], m[3][0],
+        m[0][1], m[1][1], m[2][1], m[3][1],
+        m[0][2], m[1][2], m[2][2], m[3][2],
+        m[0][3], m[1][3], m[2][3], m[3][3]
     )
 }
 
-fn to_mat4_from34(mat: &ll::HmdMatrix34_t) -> cgmath::matrix::Mat4<f32>
-{
-    cgmath::matrix::Mat4::new(
-        mat.m[0][0], mat.m[0][1], mat.m[0][2], mat.m[0][3],
-        mat.m[1][0], mat.m[1][1], mat.m[1][2], mat.m[1][3],
-        mat.m[2][0], mat.m[2][1], mat.m[2][2], mat.m[2][3],
-        0.,          0.,          0.,          1.,
+fn to_mat4_from34(mat: &ll::HmdMatrix34_t) -> Matrix4<f32> {
+    let m = &mat.m;
+    Matrix4::new(
+        m[0][0], m[1][0], m[2][0], 0.,
+        m[0][1], m[1][1], m[2][1], 0.,
+        m[0][2], m[1][2], m[2][2], 0.,
+        m[0][3], m[1][3], m[2][3], 1.
     )
 }
 
-fn to_mat34_mat4(mat: &cgmath::matrix::Mat4<f32>) -> ll::HmdMatrix34_t
-{
-    ll::HmdMatrix34_t {
-        m: [[mat.x.x, mat.x.y, mat.x.z, mat.x.w],
-            [mat.y.x, mat.y.y, mat.x.z, mat.y.w],
-            [mat.z.x, mat.z.y, mat.x.z, mat.z.w]]
-    }
+fn from_buf(buf: &[c_char]) -> String {
+    let len = buf.iter().position(|&c| c == 0).unwrap_or(buf.len());
+    buf[..len].iter().map(|&c| c as u8 as char).collect()
 }
 
-impl Hmd
-{
-    pub fn new() -> Result<Hmd, HmdError>
-    {
+impl Hmd {
+    pub fn new() -> Result<Hmd, HmdError> {
         let mut error = ll::HmdError_None;
 
-        let ptr = unsafe {
-            ll::VR_Init(&mut error as *mut ll::HmdError)
+        let ihmd = unsafe {
+            ll::VR_Init(&mut error)
         };
 
-        if ptr.is_null() {
+        if ihmd.is_null() {
             Err(HmdError::new_from_code(error))
         } else {
-            Ok(Hmd{ptr: ptr})
+            Ok(Hmd{ptr: ihmd})
         }
     }
 
-    pub fn window_bounds(&self) -> ((int, int), (uint, uint))
-    {
+    pub fn window_bounds(&self) -> ((isize, isize), (usize, usize)) {
         let mut x = 0;
         let mut y = 0;
         let mut width = 0;
@@ -308,34 +331,32 @@ impl Hmd
         unsafe {
             ll::VR_IHmd_GetWindowBounds(
                 self.ptr,
-                &mut x as *mut i32,
-                &mut y as *mut i32,
-                &mut width as *mut u32,
-                &mut height as *mut u32
+                &mut x,
+                &mut y,
+                &mut width,
+                &mut height
             );
         }
 
-        ((x as int, y as int), (width as uint, height as uint))
+        ((x as isize, y as isize), (width as usize, height as usize))
     }
 
-    pub fn recommended_render_target_size(&self) -> (uint, uint)
-    {
+    pub fn recommended_render_target_size(&self) -> (usize, usize) {
         let mut width = 0;
         let mut height = 0;
 
         unsafe {
             ll::VR_IHmd_GetRecommendedRenderTargetSize(
                 self.ptr,
-                &mut width as *mut u32,
-                &mut height as *mut u32
+                &mut width,
+                &mut height
             );
         }
 
-        (width as uint, height as uint)       
+        (width as usize, height as usize)
     }
 
-    pub fn get_eye_output_viewport(&self, eye: HmdEye) -> ((uint, uint), (uint, uint))
-    {
+    pub fn get_eye_output_viewport(&self, eye: HmdEye) -> ((usize, usize), (usize, usize)) {
         let mut x = 0;
         let mut y = 0;
         let mut width = 0;
@@ -345,18 +366,17 @@ impl Hmd
             ll::VR_IHmd_GetEyeOutputViewport(
                 self.ptr,
                 eye.to_ll(),
-                &mut x as *mut u32,
-                &mut y as *mut u32,
-                &mut width as *mut u32,
-                &mut height as *mut u32
+                &mut x,
+                &mut y,
+                &mut width,
+                &mut height
             );
         }
 
-        ((x as uint, y as uint), (width as uint, height as uint))       
+        ((x as usize, y as usize), (width as usize, height as usize))
     }
 
-    pub fn get_projection_matrix(&self, eye: HmdEye, near: f32, far: f32, proj: GraphicsAPIConvention) -> cgmath::matrix::Mat4<f32>
-    {
+    pub fn get_projection_matrix(&self, eye: HmdEye, near: f32, far: f32, proj: GraphicsAPIConvention) -> Matrix4<f32> {
         let mat = unsafe {
             ll::VR_IHmd_GetProjectionMatrix(
                 self.ptr,
@@ -370,8 +390,7 @@ impl Hmd
         to_mat4(&mat)
     }
 
-    pub fn get_projection_raw(&self, eye: HmdEye) -> (f32, f32, f32, f32)
-    {
+    pub fn get_projection_raw(&self, eye: HmdEye) -> (f32, f32, f32, f32) {
         let mut left = 0.;
         let mut right = 0.;
         let mut top = 0.;
@@ -381,18 +400,17 @@ impl Hmd
             ll::VR_IHmd_GetProjectionRaw(
                 self.ptr,
                 eye.to_ll(),
-                &mut left as *mut c_float,
-                &mut right as *mut c_float,
-                &mut top as *mut c_float,
-                &mut bottom as *mut c_float,
+                &mut left,
+                &mut right,
+                &mut top,
+                &mut bottom,
             )
         }
 
-        (left as f32, right as f32, top as f32, bottom as f32)
+        (left, right, top, bottom)
     }
 
-    pub fn compute_distortion(&self, eye: HmdEye, fu: f32, fv: f32) -> DistortionCoordinates
-    {
+    pub fn compute_distortion(&self, eye: HmdEye, fu: f32, fv: f32) -> DistortionCoordinates {
         let dc = unsafe {
             ll::VR_IHmd_ComputeDistortion(
                 self.ptr,
@@ -403,14 +421,13 @@ impl Hmd
         };
 
         DistortionCoordinates {
-            red: [dc.rfRed[0], dc.rfRed[1]],
-            blue: [dc.rfBlue[0], dc.rfBlue[1]],
-            green: [dc.rfGreen[0], dc.rfGreen[1]],
+            red: dc.rfRed,
+            green: dc.rfGreen,
+            blue: dc.rfBlue,
         }
     }
 
-    pub fn get_eye_matrix(&self, eye: HmdEye) -> cgmath::matrix::Mat4<f32>
-    {
+    pub fn get_eye_matrix(&self, eye: HmdEye) -> Matrix4<f32> {
         let mat = unsafe {
             ll::VR_IHmd_GetEyeMatrix(
                 self.ptr,
@@ -421,36 +438,34 @@ impl Hmd
         to_mat4(&mat)
     }
 
-    pub fn get_view_matrix(&self, from_now: f32) -> (cgmath::matrix::Mat4<f32>, cgmath::matrix::Mat4<f32>, HmdTrackingResult)
-    {
-        let mut left = ll::HmdMatrix44_t::zero();
-        let mut right = ll::HmdMatrix44_t::zero();
+    pub fn get_view_matrix(&self, from_now: f32) -> (Matrix4<f32>, Matrix4<f32>, HmdTrackingResult) {
+        let mut left: ll::HmdMatrix44_t = Default::default();
+        let mut right: ll::HmdMatrix44_t = Default::default();
         let mut tr = ll::TrackingResult_Uninitialized;
 
         unsafe {
             ll::VR_IHmd_GetViewMatrix(
                 self.ptr,
                 from_now as c_float,
-                &mut left as *mut ll::HmdMatrix44_t,
-                &mut right as *mut ll::HmdMatrix44_t,
-                &mut tr as *mut ll::HmdTrackingResult
+                &mut left,
+                &mut right,
+                &mut tr
             );
         }
 
         (to_mat4(&left), to_mat4(&right), HmdTrackingResult::from_ll(tr))
     }
 
-    pub fn get_world_from_head_pose(&self, from_now: f32) -> Option<(cgmath::matrix::Mat4<f32>, HmdTrackingResult)>
-    {
-        let mut mat = ll::HmdMatrix34_t::zero();
+    pub fn get_world_from_head_pose(&self, from_now: f32) -> Option<(Matrix4<f32>, HmdTrackingResult)> {
+        let mut mat: ll::HmdMatrix34_t = Default::default();
         let mut tr = ll::TrackingResult_Uninitialized;
 
         let is_valid = unsafe {
             ll::VR_IHmd_GetWorldFromHeadPose(
                 self.ptr,
                 from_now as c_float,
-                &mut mat as *mut ll::HmdMatrix34_t,
-                &mut tr as *mut ll::HmdTrackingResult
+                &mut mat,
+                &mut tr
             )
         };
 
@@ -461,55 +476,106 @@ impl Hmd
         }
     }
 
-    pub fn get_last_world_from_head_pose(&self, mat: &cgmath::matrix::Mat4<f32>) -> bool
-    {
-        let mat = to_mat34_mat4(mat);
+    pub fn get_last_world_from_head_pose(&self) -> Option<Matrix4<f32>> {
+        let mut mat: ll::HmdMatrix34_t = Default::default();
 
-        unsafe {
+        let is_valid = unsafe {
             ll::VR_IHmd_GetLastWorldFromHeadPose(
                 self.ptr,
-                &mat as *ll::HmdMatrix34_t
+                &mut mat
             )
+        };
+
+        if is_valid {
+            Some(to_mat4_from34(&mat))
+        } else {
+            None
         }
     }
 
-    pub fn will_drift_in_yaw(&self) -> bool
-    {
+    pub fn will_drift_in_yaw(&self) -> bool {
         unsafe {
             ll::VR_IHmd_WillDriftInYaw(self.ptr)
         }
     }
 
-    pub fn zero_tracking(&mut self)
-    {
+    pub fn zero_tracking(&mut self) {
         unsafe {
             ll::VR_IHmd_ZeroTracker(self.ptr)
         }
     }
 
-    pub fn get_driver_id(&self) -> ~str
-    {
-        let mut buf = std::vec::from_elem(128, 0i8);
+    pub fn get_driver_id(&self) -> String {
+        let mut buf = [0 as c_char; 128];
 
         unsafe {
             ll::VR_IHmd_GetDriverId(self.ptr,
                 buf.as_mut_ptr(),
                 buf.len() as u32
             );
-            std::str::raw::from_c_str(buf.as_ptr())
         }
+        from_buf(&buf)
     }
 
-    pub fn get_display_id(&self) -> ~str
-    {
-        let mut buf = std::vec::from_elem(128, 0i8);
+    pub fn get_display_id(&self) -> String {
+        let mut buf = [0 as c_char; 128];
 
         unsafe {
             ll::VR_IHmd_GetDisplayId(self.ptr,
                 buf.as_mut_ptr(),
                 buf.len() as u32
             );
-            std::str::raw::from_c_str(buf.as_ptr())
+        }
+        from_buf(&buf)
+    }
+}
+
+impl HeadMountedDisplay for Hmd {
+    fn description(&self) -> DisplayDescription {
+        let ((x, y), (width, height)) = self.window_bounds();
+        DisplayDescription {
+            name: self.get_display_id(),
+            resolution: (width as u32, height as u32),
+            window_position: (x as i32, y as i32)
+        }
+    }
+
+    fn recommended_render_size(&self, _: vr_common::Eye) -> (u32, u32) {
+        let (width, height) = self.recommended_render_target_size();
+        (width as u32, height as u32)
+    }
+
+    fn fov(&self, eye: vr_common::Eye) -> Fov {
+        // the raw projection is in tangents with left and top negative
+        let (raw_left, raw_right, raw_top, raw_bottom) =
+            self.get_projection_raw(HmdEye::from_common(eye));
+        Fov {
+            up: -raw_top,
+            down: raw_bottom,
+            left: -raw_left,
+            right: raw_right
+        }
+    }
+
+    fn projection(&self, eye: vr_common::Eye, znear: f32, zfar: f32) -> Matrix4<f32> {
+        self.get_projection_matrix(HmdEye::from_common(eye), znear, zfar, GraphicsAPIConvention::OpenGL)
+    }
+
+    fn eye_viewport(&self, eye: vr_common::Eye) -> Viewport {
+        let ((x, y), (width, height)) = self.get_eye_output_viewport(HmdEye::from_common(eye));
+        Viewport {
+            x: x as i32,
+            y: y as i32,
+            width: width as u32,
+            height: height as u32
         }
     }
-}
\ No newline at end of file
+
+    fn head_pose(&self, seconds_from_now: f32) -> Option<Matrix4<f32>> {
+        self.get_world_from_head_pose(seconds_from_now).map(|(pose, _)| pose)
+    }
+
+    fn recenter(&mut self) {
+        self.zero_tracking();
+    }
+}
@@ -29,7 +29,7 @@ extern "C"
 
     vr::HmdMatrix44_t VR_IHmd_GetProjectionMatrix(vr::IHmd *ihmd, vr::Hmd_Eye eEye, float fNearZ, float fFarZ, vr::GraphicsAPIConvention eProjType)
     {
-        ihmd->GetProjectionMatrix(eEye, fNearZ, fFarZ, eProjType);
+        return ihmd->GetProjectionMatrix(eEye, fNearZ, fFarZ, eProjType);
     }
 
     void VR_IHmd_GetProjectionRaw(vr::IHmd *ihmd, vr::Hmd_Eye eEye, float *pfLeft, float *pfRight, float *pfTop, float *pfBottom)
@@ -39,7 +39,7 @@ extern "C"
 
     vr::DistortionCoordinates_t VR_IHmd_ComputeDistortion(vr::IHmd *ihmd, vr::Hmd_Eye eEye, float fU, float fV)
     {
-        ihmd->ComputeDistortion(eEye, fU, fV);
+        return ihmd->ComputeDistortion(eEye, fU, fV);
     }
 
     vr::HmdMatrix44_t VR_IHmd_GetEyeMatrix(vr::IHmd *ihmd, vr::Hmd_Eye eEye)
@@ -49,22 +49,22 @@ extern "C"
 
     bool VR_IHmd_GetViewMatrix(vr::IHmd *ihmd, float fSecondsFromNow, vr::HmdMatrix44_t *pMatLeftView, vr::HmdMatrix44_t *pMatRightView, vr::HmdTrackingResult *peResult)
     {
-        ihmd->GetViewMatrix(fSecondsFromNow, pMatLeftView, pMatRightView, peResult);
+        return ihmd->GetViewMatrix(fSecondsFromNow, pMatLeftView, pMatRightView, peResult);
     }
 
     bool VR_IHmd_GetWorldFromHeadPose(vr::IHmd *ihmd, float fPredictedSecondsFromNow, vr::HmdMatrix34_t *pmPose, vr::HmdTrackingResult *peResult)
     {
-        ihmd->GetWorldFromHeadPose(fPredictedSecondsFromNow, pmPose, peResult);
+        return ihmd->GetWorldFromHeadPose(fPredictedSecondsFromNow, pmPose, peResult);
     }
 
     bool VR_IHmd_GetLastWorldFromHeadPose(vr::IHmd *ihmd, vr::HmdMatrix34_t *pmPose)
     {
-        ihmd->GetLastWorldFromHeadPose(pmPose);   
+        return ihmd->GetLastWorldFromHeadPose(pmPose);   
     }
 
     bool VR_IHmd_WillDriftInYaw(vr::IHmd *ihmd)
     {
-        ihmd->WillDriftInYaw();
+        return ihmd->WillDriftInYaw();
     }
 
     void VR_IHmd_ZeroTracker(vr::IHmd *ihmd)
@@ -74,11 +74,11 @@ extern "C"
 
     uint32_t VR_IHmd_GetDriverId(vr::IHmd *ihmd, char *pchBuffer, uint32_t unBufferLen )
     {
-        ihmd->GetDriverId(pchBuffer, unBufferLen);
+        return ihmd->GetDriverId(pchBuffer, unBufferLen);
     }
 
     uint32_t VR_IHmd_GetDisplayId(vr::IHmd *ihmd, char *pchBuffer, uint32_t unBufferLen )
     {
-        ihmd->GetDisplayId(pchBuffer, unBufferLen);
+        return ihmd->GetDisplayId(pchBuffer, unBufferLen);
     }
 }
\ No newline at end of file
@@ -0,0 +1,65 @@
+#![crate_name = "vr_common"]
+#![crate_type = "lib"]
+
+// Types and the HeadMountedDisplay trait shared by the Oculus and SteamVR
+// bindings, so an engine can choose a runtime at startup and use either
+// through the same interface.
+
+extern crate cgmath;
+
+use cgmath::Matrix4;
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum Eye {
+    Left,
+    Right
+}
+
+// field of view of one eye as the tangent of the half angle in each
+// direction, all values are positive for a view that contains the axis
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct Fov {
+    pub up: f32,
+    pub down: f32,
+    pub left: f32,
+    pub right: f32
+}
+
+// a region of the display or render target in pixels
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct Viewport {
+    pub x: i32,
+    pub y: i32,
+    pub width: u32,
+    pub height: u32
+}
+
+#[derive(Debug, Clone)]
+pub struct DisplayDescription {
+    pub name: String,
+    pub resolution: (u32, u32),
+    pub window_position: (i32, i32)
+}
+
+pub trait HeadMountedDisplay {
+    fn description(&self) -> DisplayDescription;
+
+    // size of the render target the runtime recommends for one eye
+    fn recommended_render_size(&self, eye: Eye) -> (u32, u32);
+
+    fn fov(&self, eye: Eye) -> Fov;
+
+    // OpenGL style right handed projection for the eye, depth is mapped to
+    // [-1, 1] with the near plane at -1 whatever the runtime
+    fn projection(&self, eye: Eye, znear: f32, zfar: f32) -> Matrix4<f32>;
+
+    // the region of the display the eye is presented in
+    fn eye_viewport(&self, eye: Eye) -> Viewport;
+
+    // world from head transform predicted the given number of seconds
+    // ahead, None if the runtime does not have a valid pose
+    fn head_pose(&self, seconds_from_now: f32) -> Option<Matrix4<f32>>;
+
+    // makes the current head orientation the new forward direction
+    fn recenter(&mut self);
+}